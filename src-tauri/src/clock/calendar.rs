@@ -0,0 +1,249 @@
+use chrono::{DateTime, Duration, LocalResult, NaiveDate, NaiveTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::str::FromStr;
+
+use crate::configuration::DayGrouping;
+
+// Decides which day an instant belongs to: the local date in the configured zone, with the day
+// starting at `start_hour` instead of midnight so late-night work counts towards the day before.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calendar {
+    timezone: Tz,
+    start_hour: u32,
+    grouping: DayGrouping,
+}
+
+impl Default for Calendar {
+    fn default() -> Self {
+        Calendar::new(Tz::UTC, 0)
+    }
+}
+
+impl Calendar {
+    pub fn new(timezone: Tz, start_hour: u32) -> Self {
+        Calendar {
+            timezone,
+            start_hour,
+            grouping: DayGrouping::default(),
+        }
+    }
+
+    pub fn with_grouping(mut self, grouping: DayGrouping) -> Self {
+        self.grouping = grouping;
+        self
+    }
+
+    pub fn grouping(&self) -> DayGrouping {
+        self.grouping
+    }
+
+    pub fn start_hour(&self) -> u32 {
+        self.start_hour
+    }
+
+    pub fn timezone_name(&self) -> &'static str {
+        self.timezone.name()
+    }
+
+    // Seconds east of UTC at the given instant
+    pub fn utc_offset_at(&self, instant: DateTime<Utc>) -> i32 {
+        instant
+            .with_timezone(&self.timezone)
+            .offset()
+            .fix()
+            .local_minus_utc()
+    }
+
+    // Uses the given IANA zone name, or the zone of the system when there is none
+    pub fn from_settings(timezone: Option<&str>, start_hour: u32) -> Result<Self, String> {
+        let timezone = match timezone {
+            Some(name) => parse_timezone(name)?,
+            None => system_timezone(),
+        };
+
+        Ok(Calendar::new(timezone, start_hour))
+    }
+
+    pub fn day_of(&self, instant: DateTime<Utc>) -> NaiveDate {
+        let local = instant.with_timezone(&self.timezone).naive_local();
+
+        (local - Duration::hours(self.start_hour.into())).date()
+    }
+
+    // The day of an entry started at `start_time`, which was `utc_offset` seconds ahead of UTC
+    // where it was recorded. Entries without an offset predate it being stored and fall back to
+    // the current zone.
+    pub fn entry_day(&self, start_time: DateTime<Utc>, utc_offset: Option<i32>) -> NaiveDate {
+        match (self.grouping, utc_offset) {
+            (DayGrouping::EntryZone, Some(offset)) => {
+                let local = start_time.naive_utc() + Duration::seconds(offset.into());
+
+                (local - Duration::hours(self.start_hour.into())).date()
+            }
+            _ => self.day_of(start_time),
+        }
+    }
+
+    // First instant of the day and of the day after it. Days around DST changes are 23 or 25
+    // hours long.
+    pub fn bounds(&self, day: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+        let next_day = day.succ_opt().expect("date out of range");
+
+        (self.start_of(day), self.start_of(next_day))
+    }
+
+    fn start_of(&self, day: NaiveDate) -> DateTime<Utc> {
+        let time = NaiveTime::from_hms_opt(self.start_hour, 0, 0).expect("invalid start hour");
+        let mut local = day.and_time(time);
+
+        // When clocks jump forward over the start of the day, it starts once they land
+        loop {
+            match self.timezone.from_local_datetime(&local) {
+                LocalResult::Single(start) => return start.with_timezone(&Utc),
+                LocalResult::Ambiguous(earliest, _) => return earliest.with_timezone(&Utc),
+                LocalResult::None => local += Duration::minutes(15),
+            }
+        }
+    }
+}
+
+fn parse_timezone(name: &str) -> Result<Tz, String> {
+    Tz::from_str(name).map_err(|_| format!("Unknown timezone {:?}", name))
+}
+
+fn system_timezone() -> Tz {
+    match iana_time_zone::get_timezone() {
+        Ok(name) => parse_timezone(&name).unwrap_or_else(|e| {
+            log::warn!("{}, using UTC", e);
+            Tz::UTC
+        }),
+        Err(e) => {
+            log::warn!("Could not detect the system timezone, using UTC: {}", e);
+            Tz::UTC
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn madrid(start_hour: u32) -> Calendar {
+        Calendar::new(chrono_tz::Europe::Madrid, start_hour)
+    }
+
+    fn utc(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn evening_work_stays_on_the_local_day() {
+        // 00:30 in Madrid, still the 16th in UTC
+        assert_eq!(
+            madrid(0).day_of(utc("2024-07-16T22:30:00Z")),
+            date(2024, 7, 17)
+        );
+        assert_eq!(
+            Calendar::default().day_of(utc("2024-07-16T22:30:00Z")),
+            date(2024, 7, 16)
+        );
+    }
+
+    #[test]
+    fn day_start_offset_moves_the_boundary() {
+        // 03:30 local belongs to the day before, 04:00 starts the new one
+        assert_eq!(
+            madrid(4).day_of(utc("2024-07-17T01:30:00Z")),
+            date(2024, 7, 16)
+        );
+        assert_eq!(
+            madrid(4).day_of(utc("2024-07-17T02:00:00Z")),
+            date(2024, 7, 17)
+        );
+
+        let (start, end) = madrid(4).bounds(date(2024, 7, 16));
+        assert_eq!(start, utc("2024-07-16T02:00:00Z"));
+        assert_eq!(end, utc("2024-07-17T02:00:00Z"));
+    }
+
+    #[test]
+    fn spring_forward_day_is_23_hours_long() {
+        let (start, end) = madrid(0).bounds(date(2024, 3, 31));
+
+        assert_eq!(start, utc("2024-03-30T23:00:00Z"));
+        assert_eq!(end, utc("2024-03-31T22:00:00Z"));
+        assert_eq!(end - start, Duration::hours(23));
+
+        // 23:30 local on the evening of the change
+        assert_eq!(
+            madrid(0).day_of(utc("2024-03-31T21:30:00Z")),
+            date(2024, 3, 31)
+        );
+    }
+
+    #[test]
+    fn fall_back_day_is_25_hours_long() {
+        let (start, end) = madrid(0).bounds(date(2024, 10, 27));
+
+        assert_eq!(start, utc("2024-10-26T22:00:00Z"));
+        assert_eq!(end, utc("2024-10-27T23:00:00Z"));
+        assert_eq!(end - start, Duration::hours(25));
+
+        // Both passes through 02:30 local are on the same day
+        assert_eq!(
+            madrid(0).day_of(utc("2024-10-27T00:30:00Z")),
+            date(2024, 10, 27)
+        );
+        assert_eq!(
+            madrid(0).day_of(utc("2024-10-27T01:30:00Z")),
+            date(2024, 10, 27)
+        );
+    }
+
+    #[test]
+    fn day_starting_inside_the_dst_gap_starts_when_clocks_land() {
+        // 02:00 doesn't exist in Madrid on 2024-03-31, the day starts at 03:00 local
+        let (start, _) = madrid(2).bounds(date(2024, 3, 31));
+        assert_eq!(start, utc("2024-03-31T01:00:00Z"));
+
+        // 02:00 happens twice on 2024-10-27, the day starts at the first one
+        let (start, _) = madrid(2).bounds(date(2024, 10, 27));
+        assert_eq!(start, utc("2024-10-27T00:00:00Z"));
+    }
+
+    #[test]
+    fn entries_keep_the_day_of_the_zone_they_were_recorded_in() {
+        // Started at 00:30 in Madrid, looked at later from New York
+        let start_time = utc("2024-07-16T22:30:00Z");
+        let offset = madrid(0).utc_offset_at(start_time);
+        assert_eq!(offset, 2 * 3600);
+
+        let new_york = Calendar::new(chrono_tz::America::New_York, 0);
+        assert_eq!(
+            new_york.entry_day(start_time, Some(offset)),
+            date(2024, 7, 17)
+        );
+        assert_eq!(new_york.entry_day(start_time, None), date(2024, 7, 16));
+
+        let current_zone = new_york.with_grouping(DayGrouping::CurrentZone);
+        assert_eq!(
+            current_zone.entry_day(start_time, Some(offset)),
+            date(2024, 7, 16)
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_timezones() {
+        assert!(Calendar::from_settings(Some("Europe/Atlantis"), 0).is_err());
+        assert_eq!(
+            Calendar::from_settings(Some("Asia/Tokyo"), 4).unwrap(),
+            Calendar::new(chrono_tz::Asia::Tokyo, 4)
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use chrono::{DateTime, Duration, Utc};
+use std::sync::Mutex;
+use tauri::State;
+
+use crate::configuration::ConfigurationState;
+use crate::errors::TimersError;
+use crate::timers::PomodoroScheduler;
+
+pub use calendar::Calendar;
+
+mod calendar;
+
+// Source of "now" for everything that stamps or measures time, so tests and development
+// mode can control it instead of reading the wall clock directly.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+// The application clock: system time shifted by an offset that can only be moved in
+// development mode ("time travel"), to simulate days of usage.
+#[derive(Default)]
+pub struct OffsetClock {
+    offset: Mutex<Duration>,
+}
+
+impl OffsetClock {
+    pub fn travel(&self, by: Duration) -> DateTime<Utc> {
+        let mut offset = self.offset.lock().unwrap();
+        *offset += by;
+
+        SystemClock.now() + *offset
+    }
+
+    pub fn reset(&self) {
+        *self.offset.lock().unwrap() = Duration::zero();
+    }
+}
+
+impl Clock for OffsetClock {
+    fn now(&self) -> DateTime<Utc> {
+        SystemClock.now() + *self.offset.lock().unwrap()
+    }
+}
+
+// Controllable clock for tests, it only moves when told to.
+#[cfg(test)]
+pub struct FakeClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+#[cfg(test)]
+impl FakeClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        FakeClock {
+            now: Mutex::new(now),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+}
+
+#[cfg(test)]
+impl Clock for FakeClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
+
+#[tauri::command]
+pub fn time_travel_command(
+    minutes: i64,
+    configuration: State<ConfigurationState>,
+    clock: State<OffsetClock>,
+    scheduler: State<PomodoroScheduler>,
+) -> Result<DateTime<Utc>, TimersError> {
+    log::debug!("Time travel command handler started");
+
+    if !configuration.get().development_mode {
+        return Err(TimersError::Validation(String::from(
+            "Time travel is only available in development mode",
+        )));
+    }
+
+    if minutes == 0 {
+        clock.reset();
+        scheduler.wake();
+        return Ok(clock.now());
+    }
+
+    let now = clock.travel(Duration::minutes(minutes));
+    scheduler.wake();
+    log::info!("Travelled {} minutes, it is now {}", minutes, now);
+
+    Ok(now)
+}
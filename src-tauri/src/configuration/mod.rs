@@ -1,10 +1,35 @@
-use rusqlite::config;
 use serde::Serialize;
 use std::fs::File;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::RwLock;
+use tauri::{AppHandle, Emitter, Manager, State};
+use toml_edit::DocumentMut;
+use ts_rs::TS;
 
-#[derive(Debug, Serialize)]
+use crate::clock::Calendar;
+use crate::errors::TimersError;
+use crate::timers::PomodoroScheduler;
+use overrides::{pick, Overrides};
+use paths::AppDirectories;
+
+pub use overrides::{ConfigurationSources, ValueSource};
+pub use patch::ConfigurationPatch;
+pub use settings::{
+    ActiveTimerPolicy, ConfigFile, ConfigurationError, DayGrouping, DaySettings,
+    NotificationSettings, OverlapPolicy, PomodoroSettings, TimerSettings,
+};
+pub use watcher::watch;
+
+mod overrides;
+mod patch;
+mod paths;
+mod settings;
+mod watcher;
+
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[ts(export)]
 pub struct Configuration {
     pub version: String,
     #[serde(rename = "developmentMode")]
@@ -13,79 +38,642 @@ pub struct Configuration {
     pub config_path: PathBuf,
     #[serde(rename = "dbPath")]
     pub db_path: PathBuf,
+    #[serde(rename = "logDir")]
+    pub log_dir: Option<PathBuf>,
+    #[serde(rename = "backupDir")]
+    pub backup_dir: PathBuf,
+    #[serde(rename = "exportDir")]
+    pub export_dir: PathBuf,
+    // Everything lives next to the executable, see `paths::portable_root`
+    pub portable: bool,
+    pub timers: TimerSettings,
+    pub pomodoro: PomodoroSettings,
+    pub notifications: NotificationSettings,
+    pub day: DaySettings,
+    // Unknown keys found in the configuration file
+    pub warnings: Vec<String>,
+    // Where the values that can be overridden at launch came from
+    pub sources: ConfigurationSources,
+    // Files brought over from the legacy directory on this start, logged once logging is up
+    #[serde(skip)]
+    pub moved_files: Vec<(PathBuf, PathBuf)>,
 }
 
 impl Configuration {
     pub fn init_development_config() -> Self {
         Configuration {
-            version: String::from(format!("{}-dev", env!("CARGO_PKG_VERSION"))),
+            version: format!("{}-dev", env!("CARGO_PKG_VERSION")),
             development_mode: true,
             config_path: PathBuf::from_str("./config.toml")
                 .expect("Could not create development config file pathbuf"),
             db_path: PathBuf::from_str("./file.db")
                 .expect("Could not create development db file pathbuf"),
+            // Development builds log to the terminal only
+            log_dir: None,
+            backup_dir: PathBuf::from("./backups"),
+            export_dir: PathBuf::from("./exports"),
+            portable: false,
+            timers: TimerSettings::default(),
+            pomodoro: PomodoroSettings::default(),
+            notifications: NotificationSettings::default(),
+            day: DaySettings::default(),
+            warnings: Vec::new(),
+            sources: ConfigurationSources::default(),
+            moved_files: Vec::new(),
         }
     }
 
-    fn config_path() -> PathBuf {
-        let mut config_path = PathBuf::new();
-        config_path.push(dirs::home_dir().expect("Could not load home dir"));
-        config_path.push(".config/.timers/config.toml");
-
-        println!("Loading config_path {:?}", config_path);
+    // The default file is created empty so there is something to edit
+    fn create_config_file(config_path: &Path) {
+        log::debug!("Loading configuration file {:?}", config_path);
 
         if let Some(parent) = config_path.parent() {
             if !parent.exists() {
                 log::info!("Creating configuration directory for {:?}", &config_path);
                 std::fs::create_dir_all(parent).expect("Could not create configuration directory");
-                println!("Directory created: {:?}", parent);
+                log::debug!("Directory created: {:?}", parent);
             }
         }
 
         if !config_path.exists() {
             log::info!("Creating configuration file {:?}", &config_path);
-            File::create(&config_path).expect("Could not create config file");
+            File::create(config_path).expect("Could not create config file");
         }
+    }
 
-        config_path
+    pub fn load() -> Result<Self, ConfigurationError> {
+        let environment = Overrides::from_environment(|name| std::env::var(name).ok())?;
+        let command_line = Overrides::from_args(std::env::args().skip(1))?;
+
+        Configuration::resolve(environment, command_line)
     }
 
-    fn db_path() -> PathBuf {
-        let mut db_path = PathBuf::new();
-        db_path.push(dirs::home_dir().expect("Could not load home dir"));
-        db_path.push(".config/.timers/db.sqlite");
+    // Layers, lowest priority first: build defaults, the configuration file, `TIMERS_*`
+    // environment variables, command line flags. The configuration path can't come from the
+    // file itself, and the development mode only picks the defaults.
+    fn resolve(
+        environment: Overrides,
+        command_line: Overrides,
+    ) -> Result<Self, ConfigurationError> {
+        let development_mode = pick(environment.development_mode, command_line.development_mode);
 
-        if let Some(parent) = db_path.parent() {
-            if !parent.exists() {
-                std::fs::create_dir_all(parent).expect("Could not create configuration directory");
-                println!("Directory created: {:?}", parent);
+        let mut configuration = match development_mode {
+            Some((true, _)) => Configuration::init_development_config(),
+            Some((false, _)) => Configuration::init_config(),
+            None if cfg!(debug_assertions) => Configuration::init_development_config(),
+            None => Configuration::init_config(),
+        };
+
+        if let Some((_, source)) = development_mode {
+            configuration.sources.development_mode = source;
+        }
+
+        if let Some((config_path, source)) = pick(environment.config_path, command_line.config_path)
+        {
+            configuration.config_path = config_path;
+            configuration.sources.config_path = source;
+        }
+
+        if let Some((db_path, source)) = pick(environment.db_path, command_line.db_path) {
+            configuration.db_path = db_path;
+            configuration.sources.db_path = source;
+        }
+
+        if !configuration.development_mode {
+            // A portable install doesn't reach into the home directory
+            if !configuration.portable {
+                configuration.move_legacy_files()?;
+            }
+
+            if configuration.sources.config_path == ValueSource::Default {
+                Configuration::create_config_file(&configuration.config_path);
             }
         }
 
-        if !db_path.exists() {
-            File::create(&db_path).expect("Could not create config file");
+        configuration.read_file()?;
+
+        Ok(configuration)
+    }
+
+    // Re-reads the configuration file on top of the running settings. The database stays
+    // where it is until the next start.
+    pub fn reload(&self) -> Result<Self, ConfigurationError> {
+        let mut reloaded = self.clone();
+        reloaded.read_file()?;
+        self.keep_database(&mut reloaded);
+
+        Ok(reloaded)
+    }
+
+    // Puts the database in use back, along with where its path came from
+    fn keep_database(&self, changed: &mut Self) {
+        if changed.db_path != self.db_path {
+            log::warn!(
+                "Database path changed to {:?}, restart to use it",
+                changed.db_path
+            );
+            changed.db_path = self.db_path.clone();
+            changed.sources.db_path = self.sources.db_path;
+        }
+    }
+
+    fn read_file(&mut self) -> Result<(), ConfigurationError> {
+        let contents = self.read_contents()?;
+        let (file, unknown_keys) = ConfigFile::parse(&contents)
+            .map_err(|e| ConfigurationError::Parse(self.config_path.clone(), e))?;
+
+        self.apply(file, unknown_keys)
+    }
+
+    // The development file is optional, a missing one reads as empty and means defaults
+    fn read_contents(&self) -> Result<String, ConfigurationError> {
+        if !self.config_path.exists() {
+            return Ok(String::new());
         }
 
-        db_path
+        std::fs::read_to_string(&self.config_path)
+            .map_err(|e| ConfigurationError::Io(self.config_path.clone(), e))
     }
 
-    pub fn load() -> Self {
-        if cfg!(debug_assertions) {
-            return Configuration::init_development_config();
+    // Takes the settings of a parsed configuration file over the current ones
+    pub fn apply(
+        &mut self,
+        file: ConfigFile,
+        unknown_keys: Vec<String>,
+    ) -> Result<(), ConfigurationError> {
+        file.validate()?;
+
+        // A database given at launch wins over the file
+        if let Some(database_path) = file.storage.database_path {
+            if self.sources.db_path <= ValueSource::ConfigFile {
+                self.db_path = resolve_path(&self.config_path, database_path);
+                self.sources.db_path = ValueSource::ConfigFile;
+            }
         }
 
+        self.timers = file.timers;
+        self.pomodoro = file.pomodoro;
+        self.notifications = file.notifications;
+        self.day = file.day;
+        self.warnings = unknown_keys
+            .into_iter()
+            .map(|key| format!("Unknown configuration key {}", key))
+            .collect();
+
+        Ok(())
+    }
+
+    // How instants are grouped into days. The timezone was checked when the file was read.
+    pub fn calendar(&self) -> Calendar {
+        Calendar::from_settings(self.day.timezone.as_deref(), self.day.start_hour)
+            .unwrap_or_else(|e| {
+                log::warn!("{}, using UTC", e);
+                Calendar::default()
+            })
+            .with_grouping(self.day.group_by)
+    }
+
+    // Writes a settings patch to the configuration file, keeping its comments and layout, and
+    // returns the configuration it results in. Nothing is written if that would be invalid.
+    pub fn update(&self, patch: &ConfigurationPatch) -> Result<Self, ConfigurationError> {
+        let contents = self.read_contents()?;
+
+        // Reports a broken file with its line before trying to edit it
+        ConfigFile::parse(&contents)
+            .map_err(|e| ConfigurationError::Parse(self.config_path.clone(), e))?;
+        let mut document: DocumentMut = contents.parse().map_err(|e| {
+            ConfigurationError::Invalid(format!("Could not edit configuration file: {}", e))
+        })?;
+
+        patch.apply_to(&mut document);
+        let updated_contents = document.to_string();
+
+        let (file, unknown_keys) = ConfigFile::parse(&updated_contents)
+            .map_err(|e| ConfigurationError::Parse(self.config_path.clone(), e))?;
+        let mut updated = self.clone();
+        updated.apply(file, unknown_keys)?;
+        self.keep_database(&mut updated);
+
+        write_atomically(&self.config_path, &updated_contents)
+            .map_err(|e| ConfigurationError::Io(self.config_path.clone(), e))?;
+
+        Ok(updated)
+    }
+
+    // Brings files over from the legacy directory to the default locations still in use
+    fn move_legacy_files(&mut self) -> Result<(), ConfigurationError> {
+        let legacy_dir = match paths::legacy_dir() {
+            Some(legacy_dir) => legacy_dir,
+            None => return Ok(()),
+        };
+
+        let config_path = (self.sources.config_path == ValueSource::Default)
+            .then_some(self.config_path.as_path());
+        let db_path =
+            (self.sources.db_path == ValueSource::Default).then_some(self.db_path.as_path());
+
+        self.moved_files = paths::move_legacy_files(&legacy_dir, config_path, db_path)
+            .map_err(|e| ConfigurationError::Io(legacy_dir.clone(), e))?;
+
+        Ok(())
+    }
+
+    fn init_config() -> Self {
+        let portable_root = paths::portable_root();
+        let directories = match &portable_root {
+            Some(root) => AppDirectories::portable(root),
+            None => AppDirectories::system(),
+        };
+
         Configuration {
             version: String::from(env!("CARGO_PKG_VERSION")),
             development_mode: false,
-            config_path: Configuration::config_path(),
-            db_path: Configuration::db_path(),
+            config_path: directories.config_path(),
+            db_path: directories.db_path(),
+            log_dir: Some(directories.log_dir()),
+            backup_dir: directories.backup_dir(),
+            export_dir: directories.export_dir(),
+            portable: portable_root.is_some(),
+            timers: TimerSettings::default(),
+            pomodoro: PomodoroSettings::default(),
+            notifications: NotificationSettings::default(),
+            day: DaySettings::default(),
+            warnings: Vec::new(),
+            sources: ConfigurationSources::default(),
+            moved_files: Vec::new(),
+        }
+    }
+}
+
+// The configuration the app runs with. Commands read a snapshot of it, settings edits swap it
+// for a new one.
+pub struct ConfigurationState {
+    current: RwLock<Configuration>,
+}
+
+impl ConfigurationState {
+    pub fn new(configuration: Configuration) -> Self {
+        ConfigurationState {
+            current: RwLock::new(configuration),
         }
     }
+
+    pub fn get(&self) -> Configuration {
+        self.current.read().unwrap().clone()
+    }
+
+    pub fn set(&self, configuration: Configuration) {
+        *self.current.write().unwrap() = configuration;
+    }
+}
+
+// Paths in the configuration file are relative to the file itself, not the working directory
+fn resolve_path(config_path: &Path, path: PathBuf) -> PathBuf {
+    match config_path.parent() {
+        Some(parent) if path.is_relative() => parent.join(path),
+        _ => path,
+    }
+}
+
+// Replaces the file in one step, so a crash halfway through can't leave it truncated
+fn write_atomically(path: &Path, contents: &str) -> std::io::Result<()> {
+    let temporary_path = path.with_extension("toml.tmp");
+
+    let mut file = File::create(&temporary_path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+
+    std::fs::rename(&temporary_path, path)
+}
+
+#[tauri::command]
+pub fn load_configuration_command(configuration: State<ConfigurationState>) -> Configuration {
+    configuration.get()
+}
+
+// Swaps the running configuration and lets the rest of the app know
+fn publish(app: &AppHandle, configuration: Configuration) {
+    app.state::<ConfigurationState>().set(configuration.clone());
+    app.state::<PomodoroScheduler>().wake();
+
+    if let Err(e) = app.emit("configuration-changed", configuration) {
+        log::error!("Could not emit configuration change: {}", e);
+    }
 }
 
 #[tauri::command]
-pub fn load_configuration_command() -> String {
-    let configuration = Configuration::load();
+pub fn update_configuration_command(
+    patch: ConfigurationPatch,
+    app: AppHandle,
+    configuration: State<ConfigurationState>,
+) -> Result<Configuration, TimersError> {
+    log::debug!("Update configuration command handler started");
+
+    let updated = configuration.get().update(&patch)?;
+    publish(&app, updated.clone());
+    log::info!("Configuration updated");
+
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(contents: &str) -> Result<Configuration, ConfigurationError> {
+        let mut configuration = Configuration::init_development_config();
+        configuration.config_path = PathBuf::from("/etc/timers/config.toml");
+
+        let (file, unknown_keys) = ConfigFile::parse(contents)
+            .map_err(|e| ConfigurationError::Parse(configuration.config_path.clone(), e))?;
+        configuration.apply(file, unknown_keys)?;
+
+        Ok(configuration)
+    }
+
+    #[test]
+    fn empty_file_keeps_defaults() {
+        let configuration = apply("").unwrap();
+
+        assert_eq!(configuration.pomodoro, PomodoroSettings::default());
+        assert_eq!(configuration.notifications, NotificationSettings::default());
+        assert_eq!(configuration.day, DaySettings::default());
+        assert_eq!(configuration.db_path, PathBuf::from("./file.db"));
+        assert!(configuration.warnings.is_empty());
+    }
+
+    #[test]
+    fn reads_every_section() {
+        let configuration = apply(
+            r#"
+            # Long focus sessions
+            [timers]
+            active_timer_policy = "reject"
+            overlap_policy = "split"
+
+            [pomodoro]
+            work_minutes = 50
+            cycles_before_long_break = 2
+
+            [notifications]
+            breaks = false
+
+            [day]
+            start_hour = 4
+            timezone = "Europe/Madrid"
+            group_by = "current_zone"
+
+            [storage]
+            database_path = "data/timers.sqlite"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            configuration.timers.active_timer_policy,
+            ActiveTimerPolicy::Reject
+        );
+        assert_eq!(configuration.timers.overlap_policy, OverlapPolicy::Split);
+        assert_eq!(configuration.pomodoro.work_minutes, 50);
+        assert_eq!(configuration.pomodoro.short_break_minutes, 5);
+        assert_eq!(configuration.pomodoro.cycles_before_long_break, 2);
+        assert!(!configuration.notifications.breaks);
+        assert!(configuration.notifications.timer_finished);
+        assert_eq!(configuration.day.start_hour, 4);
+        assert_eq!(configuration.day.timezone.as_deref(), Some("Europe/Madrid"));
+        assert_eq!(configuration.day.group_by, DayGrouping::CurrentZone);
+        assert_eq!(
+            configuration.db_path,
+            PathBuf::from("/etc/timers/data/timers.sqlite")
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_warnings() {
+        let configuration = apply(
+            "
+            [pomodoro]
+            wrok_minutes = 30
+
+            [colours]
+            accent = 'red'
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(configuration.pomodoro, PomodoroSettings::default());
+        assert_eq!(
+            configuration.warnings,
+            vec![
+                "Unknown configuration key pomodoro.wrok_minutes",
+                "Unknown configuration key colours",
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_errors_name_the_line() {
+        let syntax = apply("[pomodoro]\nwork_minutes = = 3\n").unwrap_err();
+        assert!(syntax.to_string().contains("line 2"), "{}", syntax);
+
+        let wrong_type = apply("[day]\n\nstart_hour = \"four\"\n").unwrap_err();
+        assert!(wrong_type.to_string().contains("line 3"), "{}", wrong_type);
+    }
+
+    fn in_directory(dir: &tempfile::TempDir, contents: Option<&str>) -> Configuration {
+        let mut configuration = Configuration::init_development_config();
+        configuration.config_path = dir.path().join("config.toml");
+
+        if let Some(contents) = contents {
+            std::fs::write(&configuration.config_path, contents).unwrap();
+        }
+
+        configuration
+    }
+
+    #[test]
+    fn update_keeps_comments_and_other_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let original = "# My timers\n[pomodoro]\n# Long sessions\nwork_minutes = 50\nlong_break_minutes = 30\n";
+        let configuration = in_directory(&dir, Some(original));
+
+        let patch: ConfigurationPatch = serde_json::from_str(
+            r#"{ "pomodoro": { "work_minutes": 45 }, "notifications": { "breaks": false } }"#,
+        )
+        .unwrap();
+        let updated = configuration.update(&patch).unwrap();
+
+        assert_eq!(updated.pomodoro.work_minutes, 45);
+        assert_eq!(updated.pomodoro.long_break_minutes, 30);
+        assert!(!updated.notifications.breaks);
+
+        let written = std::fs::read_to_string(&configuration.config_path).unwrap();
+        assert!(written.contains("# My timers"));
+        assert!(written.contains("# Long sessions\nwork_minutes = 45"));
+        assert!(written.contains("long_break_minutes = 30"));
+        assert!(written.contains("[notifications]\nbreaks = false"));
+    }
 
-    serde_json::to_string(&configuration).unwrap()
+    #[test]
+    fn invalid_update_leaves_the_file_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let original = "[pomodoro]\nwork_minutes = 50\n";
+        let configuration = in_directory(&dir, Some(original));
+
+        let patch: ConfigurationPatch =
+            serde_json::from_str(r#"{ "day": { "start_hour": 30 } }"#).unwrap();
+
+        assert!(matches!(
+            configuration.update(&patch),
+            Err(ConfigurationError::Invalid(_))
+        ));
+        assert_eq!(
+            std::fs::read_to_string(&configuration.config_path).unwrap(),
+            original
+        );
+    }
+
+    #[test]
+    fn update_creates_a_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let configuration = in_directory(&dir, None);
+
+        let patch: ConfigurationPatch = serde_json::from_str(
+            r#"{ "timers": { "active_timer_policy": "reject" }, "day": { "timezone": "Asia/Tokyo" } }"#,
+        )
+        .unwrap();
+        configuration.update(&patch).unwrap();
+
+        let reloaded =
+            apply(&std::fs::read_to_string(&configuration.config_path).unwrap()).unwrap();
+        assert_eq!(
+            reloaded.timers.active_timer_policy,
+            ActiveTimerPolicy::Reject
+        );
+        assert_eq!(reloaded.day.timezone.as_deref(), Some("Asia/Tokyo"));
+    }
+
+    #[test]
+    fn reload_picks_up_edits_and_keeps_the_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let configuration = in_directory(&dir, Some("[pomodoro]\nwork_minutes = 50\n"));
+        let configuration = configuration.reload().unwrap();
+        assert_eq!(configuration.pomodoro.work_minutes, 50);
+
+        std::fs::write(
+            &configuration.config_path,
+            "[pomodoro]\nwork_minutes = 40\n[storage]\ndatabase_path = 'other.db'\n",
+        )
+        .unwrap();
+        let reloaded = configuration.reload().unwrap();
+
+        assert_eq!(reloaded.pomodoro.work_minutes, 40);
+        assert_eq!(reloaded.db_path, configuration.db_path);
+        assert_eq!(reloaded.sources.db_path, configuration.sources.db_path);
+
+        let updated = configuration
+            .update(&ConfigurationPatch::default())
+            .unwrap();
+        assert_eq!(updated.db_path, configuration.db_path);
+        assert_eq!(updated.sources.db_path, configuration.sources.db_path);
+    }
+
+    #[test]
+    fn invalid_edit_is_not_reloaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let configuration = in_directory(&dir, Some("[pomodoro]\nwork_minutes = 50\n"));
+
+        std::fs::write(&configuration.config_path, "[pomodoro]\nwork_minutes = \n").unwrap();
+        assert!(matches!(
+            configuration.reload(),
+            Err(ConfigurationError::Parse(_, _))
+        ));
+
+        std::fs::write(&configuration.config_path, "[pomodoro]\nwork_minutes = 0\n").unwrap();
+        assert!(matches!(
+            configuration.reload(),
+            Err(ConfigurationError::Invalid(_))
+        ));
+    }
+
+    fn args(args: &[&str]) -> Overrides {
+        Overrides::from_args(args.iter().map(|a| a.to_string())).unwrap()
+    }
+
+    #[test]
+    fn reads_flags_in_both_forms() {
+        let overrides = args(&["-psn_0_123", "--dev", "--config", "a.toml", "--db=b.db"]);
+
+        assert_eq!(overrides.development_mode, Some(true));
+        assert_eq!(overrides.config_path, Some(PathBuf::from("a.toml")));
+        assert_eq!(overrides.db_path, Some(PathBuf::from("b.db")));
+
+        assert_eq!(args(&["--dev=false"]).development_mode, Some(false));
+        assert!(Overrides::from_args(vec![String::from("--db")]).is_err());
+    }
+
+    #[test]
+    fn reads_environment_variables() {
+        let overrides = Overrides::from_environment(|name| match name {
+            "TIMERS_DEV" => Some(String::from("1")),
+            "TIMERS_DB" => Some(String::from("/tmp/scratch.db")),
+            _ => None,
+        })
+        .unwrap();
+
+        assert_eq!(overrides.development_mode, Some(true));
+        assert_eq!(overrides.db_path, Some(PathBuf::from("/tmp/scratch.db")));
+        assert_eq!(overrides.config_path, None);
+
+        assert!(Overrides::from_environment(|_| Some(String::from("maybe"))).is_err());
+    }
+
+    #[test]
+    fn later_layers_win() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.toml");
+        std::fs::write(&config_path, "[storage]\ndatabase_path = 'from-file.db'\n").unwrap();
+        let config = config_path.to_str().unwrap();
+
+        let file_only =
+            Configuration::resolve(Overrides::default(), args(&["--dev", "--config", config]))
+                .unwrap();
+        assert_eq!(file_only.db_path, dir.path().join("from-file.db"));
+        assert_eq!(file_only.sources.db_path, ValueSource::ConfigFile);
+        assert_eq!(file_only.sources.config_path, ValueSource::CommandLine);
+        assert_eq!(file_only.sources.development_mode, ValueSource::CommandLine);
+
+        let environment = Overrides {
+            db_path: Some(PathBuf::from("from-env.db")),
+            ..Overrides::default()
+        };
+        let from_env =
+            Configuration::resolve(environment.clone(), args(&["--dev", "--config", config]))
+                .unwrap();
+        assert_eq!(from_env.db_path, PathBuf::from("from-env.db"));
+        assert_eq!(from_env.sources.db_path, ValueSource::Environment);
+
+        let from_flag = Configuration::resolve(
+            environment,
+            args(&["--dev", "--config", config, "--db", "from-flag.db"]),
+        )
+        .unwrap();
+        assert_eq!(from_flag.db_path, PathBuf::from("from-flag.db"));
+        assert_eq!(from_flag.sources.db_path, ValueSource::CommandLine);
+
+        // Reloading the file doesn't take the database back
+        assert_eq!(from_flag.reload().unwrap().db_path, from_flag.db_path);
+    }
+
+    #[test]
+    fn rejects_out_of_range_values() {
+        let zero = apply("[pomodoro]\nwork_minutes = 0").unwrap_err();
+        assert!(zero.to_string().contains("pomodoro.work_minutes"));
+
+        let hour = apply("[day]\nstart_hour = 24").unwrap_err();
+        assert!(hour.to_string().contains("day.start_hour"));
+
+        let timezone = apply("[day]\ntimezone = \"Mars/Olympus_Mons\"").unwrap_err();
+        assert!(timezone.to_string().contains("day.timezone"));
+    }
 }
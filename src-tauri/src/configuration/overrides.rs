@@ -0,0 +1,108 @@
+use serde::Serialize;
+use std::path::PathBuf;
+use ts_rs::TS;
+
+use super::settings::ConfigurationError;
+
+// Where a resolved value came from. Later layers win, in the order listed here.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum ValueSource {
+    #[default]
+    Default,
+    ConfigFile,
+    Environment,
+    CommandLine,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, TS)]
+#[ts(export)]
+pub struct ConfigurationSources {
+    #[serde(rename = "developmentMode")]
+    pub development_mode: ValueSource,
+    #[serde(rename = "configurationPath")]
+    pub config_path: ValueSource,
+    #[serde(rename = "dbPath")]
+    pub db_path: ValueSource,
+}
+
+// Values given at launch, either as `--config`, `--db` and `--dev` flags or as `TIMERS_CONFIG`,
+// `TIMERS_DB` and `TIMERS_DEV` environment variables.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Overrides {
+    pub development_mode: Option<bool>,
+    pub config_path: Option<PathBuf>,
+    pub db_path: Option<PathBuf>,
+}
+
+impl Overrides {
+    pub fn from_environment(
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ConfigurationError> {
+        let development_mode = match var("TIMERS_DEV") {
+            Some(value) => Some(parse_flag("TIMERS_DEV", &value)?),
+            None => None,
+        };
+
+        Ok(Overrides {
+            development_mode,
+            config_path: var("TIMERS_CONFIG").map(PathBuf::from),
+            db_path: var("TIMERS_DB").map(PathBuf::from),
+        })
+    }
+
+    // Arguments we don't know are skipped, the OS and Tauri may pass their own
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, ConfigurationError> {
+        let mut overrides = Overrides::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+
+            match flag.as_str() {
+                "--dev" => {
+                    overrides.development_mode = Some(match inline_value {
+                        Some(value) => parse_flag("--dev", &value)?,
+                        None => true,
+                    })
+                }
+                "--config" | "--db" => {
+                    let path = inline_value.or_else(|| args.next()).ok_or_else(|| {
+                        ConfigurationError::Invalid(format!("{} needs a path", flag))
+                    })?;
+
+                    if flag == "--config" {
+                        overrides.config_path = Some(PathBuf::from(path));
+                    } else {
+                        overrides.db_path = Some(PathBuf::from(path));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(overrides)
+    }
+}
+
+// The value of the highest layer that sets it, along with that layer
+pub fn pick<T>(environment: Option<T>, command_line: Option<T>) -> Option<(T, ValueSource)> {
+    command_line
+        .map(|v| (v, ValueSource::CommandLine))
+        .or_else(|| environment.map(|v| (v, ValueSource::Environment)))
+}
+
+fn parse_flag(name: &str, value: &str) -> Result<bool, ConfigurationError> {
+    match value.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" | "" => Ok(false),
+        _ => Err(ConfigurationError::Invalid(format!(
+            "{} must be true or false, got {:?}",
+            name, value
+        ))),
+    }
+}
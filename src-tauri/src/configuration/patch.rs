@@ -0,0 +1,193 @@
+use serde::Deserialize;
+use toml_edit::{DocumentMut, Item, Table, TableLike, Value};
+use ts_rs::TS;
+
+use super::settings::{ActiveTimerPolicy, DayGrouping, OverlapPolicy};
+
+// A partial settings change sent by the frontend. Only the keys that are set are written, the
+// rest of the file is left as it is.
+#[derive(Debug, Default, Deserialize, TS)]
+#[serde(default)]
+#[ts(export)]
+pub struct ConfigurationPatch {
+    #[ts(optional)]
+    pub timers: Option<TimerSettingsPatch>,
+    #[ts(optional)]
+    pub pomodoro: Option<PomodoroSettingsPatch>,
+    #[ts(optional)]
+    pub notifications: Option<NotificationSettingsPatch>,
+    #[ts(optional)]
+    pub day: Option<DaySettingsPatch>,
+}
+
+#[derive(Debug, Default, Deserialize, TS)]
+#[serde(default)]
+#[ts(export)]
+pub struct TimerSettingsPatch {
+    #[ts(optional)]
+    pub active_timer_policy: Option<ActiveTimerPolicy>,
+    #[ts(optional)]
+    pub overlap_policy: Option<OverlapPolicy>,
+}
+
+#[derive(Debug, Default, Deserialize, TS)]
+#[serde(default)]
+#[ts(export)]
+pub struct PomodoroSettingsPatch {
+    #[ts(optional)]
+    pub work_minutes: Option<u32>,
+    #[ts(optional)]
+    pub short_break_minutes: Option<u32>,
+    #[ts(optional)]
+    pub long_break_minutes: Option<u32>,
+    #[ts(optional)]
+    pub cycles_before_long_break: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize, TS)]
+#[serde(default)]
+#[ts(export)]
+pub struct NotificationSettingsPatch {
+    #[ts(optional)]
+    pub timer_started: Option<bool>,
+    #[ts(optional)]
+    pub timer_finished: Option<bool>,
+    #[ts(optional)]
+    pub timer_cancelled: Option<bool>,
+    #[ts(optional)]
+    pub breaks: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize, TS)]
+#[serde(default)]
+#[ts(export)]
+pub struct DaySettingsPatch {
+    #[ts(optional)]
+    pub start_hour: Option<u32>,
+    // An empty string goes back to the system zone
+    #[ts(optional)]
+    pub timezone: Option<String>,
+    #[ts(optional)]
+    pub group_by: Option<DayGrouping>,
+}
+
+impl ConfigurationPatch {
+    // Writes the patched keys into the document, creating sections as needed. Comments and
+    // formatting of everything else survive.
+    pub fn apply_to(&self, document: &mut DocumentMut) {
+        if let Some(timers) = &self.timers {
+            let section = section(document, "timers");
+            if let Some(policy) = timers.active_timer_policy {
+                set(section, "active_timer_policy", policy_name(policy).into());
+            }
+            if let Some(policy) = timers.overlap_policy {
+                set(
+                    section,
+                    "overlap_policy",
+                    overlap_policy_name(policy).into(),
+                );
+            }
+        }
+
+        if let Some(pomodoro) = &self.pomodoro {
+            let section = section(document, "pomodoro");
+            set_integer(section, "work_minutes", pomodoro.work_minutes);
+            set_integer(section, "short_break_minutes", pomodoro.short_break_minutes);
+            set_integer(section, "long_break_minutes", pomodoro.long_break_minutes);
+            set_integer(
+                section,
+                "cycles_before_long_break",
+                pomodoro.cycles_before_long_break,
+            );
+        }
+
+        if let Some(notifications) = &self.notifications {
+            let section = section(document, "notifications");
+            set_bool(section, "timer_started", notifications.timer_started);
+            set_bool(section, "timer_finished", notifications.timer_finished);
+            set_bool(section, "timer_cancelled", notifications.timer_cancelled);
+            set_bool(section, "breaks", notifications.breaks);
+        }
+
+        if let Some(day) = &self.day {
+            let section = section(document, "day");
+            set_integer(section, "start_hour", day.start_hour);
+
+            match day.timezone.as_deref() {
+                Some("") => {
+                    section.remove("timezone");
+                }
+                Some(timezone) => {
+                    set(section, "timezone", timezone.into());
+                }
+                None => {}
+            }
+
+            if let Some(group_by) = day.group_by {
+                set(section, "group_by", grouping_name(group_by).into());
+            }
+        }
+    }
+}
+
+// The section as written in the file, either `[name]` or an inline table. Anything else under
+// that name can't hold settings and is replaced.
+fn section<'d>(document: &'d mut DocumentMut, name: &str) -> &'d mut dyn TableLike {
+    let item = document
+        .entry(name)
+        .or_insert_with(|| Item::Table(Table::new()));
+
+    if item.as_table_like().is_none() {
+        *item = Item::Table(Table::new());
+    }
+
+    item.as_table_like_mut()
+        .expect("configuration section is a table")
+}
+
+// Replaces only the value of an existing key, so comments attached to the key stay put
+fn set(section: &mut dyn TableLike, key: &str, mut new_value: Value) {
+    match section.get_mut(key).and_then(Item::as_value_mut) {
+        Some(current) => {
+            *new_value.decor_mut() = current.decor().clone();
+            *current = new_value;
+        }
+        None => {
+            section.insert(key, Item::Value(new_value));
+        }
+    }
+}
+
+fn set_integer(section: &mut dyn TableLike, key: &str, new_value: Option<u32>) {
+    if let Some(v) = new_value {
+        set(section, key, i64::from(v).into());
+    }
+}
+
+fn set_bool(section: &mut dyn TableLike, key: &str, new_value: Option<bool>) {
+    if let Some(v) = new_value {
+        set(section, key, v.into());
+    }
+}
+
+fn policy_name(policy: ActiveTimerPolicy) -> &'static str {
+    match policy {
+        ActiveTimerPolicy::StopPrevious => "stop_previous",
+        ActiveTimerPolicy::Reject => "reject",
+    }
+}
+
+fn overlap_policy_name(policy: OverlapPolicy) -> &'static str {
+    match policy {
+        OverlapPolicy::Reject => "reject",
+        OverlapPolicy::Trim => "trim",
+        OverlapPolicy::Split => "split",
+    }
+}
+
+fn grouping_name(grouping: DayGrouping) -> &'static str {
+    match grouping {
+        DayGrouping::EntryZone => "entry_zone",
+        DayGrouping::CurrentZone => "current_zone",
+    }
+}
@@ -0,0 +1,236 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Where the app keeps its files outside development mode: settings in the config directory,
+// the database in the data directory and logs in the state directory ($XDG_CONFIG_HOME,
+// $XDG_DATA_HOME and $XDG_STATE_HOME on Linux, the platform equivalents elsewhere).
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppDirectories {
+    pub config_dir: PathBuf,
+    pub data_dir: PathBuf,
+    pub state_dir: PathBuf,
+}
+
+impl AppDirectories {
+    pub fn system() -> Self {
+        let data_dir = dirs::data_dir().expect("Could not find the data directory");
+
+        AppDirectories {
+            config_dir: dirs::config_dir()
+                .expect("Could not find the configuration directory")
+                .join("timers"),
+            // Only Linux has a separate state directory
+            state_dir: dirs::state_dir()
+                .unwrap_or_else(|| data_dir.clone())
+                .join("timers"),
+            data_dir: data_dir.join("timers"),
+        }
+    }
+
+    pub fn config_path(&self) -> PathBuf {
+        self.config_dir.join("config.toml")
+    }
+
+    pub fn db_path(&self) -> PathBuf {
+        self.data_dir.join("db.sqlite")
+    }
+
+    pub fn log_dir(&self) -> PathBuf {
+        self.state_dir.clone()
+    }
+
+    pub fn backup_dir(&self) -> PathBuf {
+        self.data_dir.join("backups")
+    }
+
+    pub fn export_dir(&self) -> PathBuf {
+        self.data_dir.join("exports")
+    }
+
+    // A portable install keeps everything in the directory of the executable, so it can live
+    // on a USB stick or in a synced folder.
+    pub fn portable(root: &Path) -> Self {
+        AppDirectories {
+            config_dir: root.to_path_buf(),
+            data_dir: root.to_path_buf(),
+            state_dir: root.join("logs"),
+        }
+    }
+}
+
+// Placed next to the executable to switch to portable mode
+const PORTABLE_MARKER: &str = "timers.portable";
+
+// The directory of the running executable when it is marked as portable
+pub fn portable_root() -> Option<PathBuf> {
+    let executable = std::env::current_exe().ok()?;
+
+    portable_root_of(&executable)
+}
+
+fn portable_root_of(executable: &Path) -> Option<PathBuf> {
+    let root = executable.parent()?;
+
+    root.join(PORTABLE_MARKER)
+        .is_file()
+        .then(|| root.to_path_buf())
+}
+
+// Versions up to 0.0.x kept everything in `~/.config/.timers`
+pub fn legacy_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".config/.timers"))
+}
+
+// SQLite keeps uncommitted data next to the database, it has to move along with it
+const DATABASE_SIDECARS: [&str; 3] = ["-wal", "-shm", "-journal"];
+
+// Moves the files of the legacy directory to their new homes, skipping any whose target
+// already exists so nothing is overwritten. Returns the moves that were made.
+pub fn move_legacy_files(
+    legacy_dir: &Path,
+    config_path: Option<&Path>,
+    db_path: Option<&Path>,
+) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    let mut moved = Vec::new();
+
+    if !legacy_dir.is_dir() {
+        return Ok(moved);
+    }
+
+    if let Some(config_path) = config_path {
+        let from = legacy_dir.join("config.toml");
+        if move_file(&from, config_path)? {
+            moved.push((from, config_path.to_path_buf()));
+        }
+    }
+
+    if let Some(db_path) = db_path {
+        let from = legacy_dir.join("db.sqlite");
+        if move_file(&from, db_path)? {
+            moved.push((from.clone(), db_path.to_path_buf()));
+
+            for suffix in DATABASE_SIDECARS {
+                let sidecar = with_suffix(&from, suffix);
+                let target = with_suffix(db_path, suffix);
+                if move_file(&sidecar, &target)? {
+                    moved.push((sidecar, target));
+                }
+            }
+        }
+    }
+
+    // Only an empty directory is removed, anything we don't know about stays
+    if fs::read_dir(legacy_dir)?.next().is_none() {
+        fs::remove_dir(legacy_dir)?;
+    }
+
+    Ok(moved)
+}
+
+fn move_file(from: &Path, to: &Path) -> io::Result<bool> {
+    if !from.is_file() || to.exists() {
+        return Ok(false);
+    }
+
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Renaming fails across file systems. Copy next to the target first, so an interrupted
+    // copy never looks like a finished move on the next run.
+    if fs::rename(from, to).is_err() {
+        let partial = with_suffix(to, ".partial");
+        fs::copy(from, &partial)?;
+        fs::rename(&partial, to)?;
+        fs::remove_file(from)?;
+    }
+
+    Ok(true)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(suffix);
+
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn legacy(dir: &tempfile::TempDir, files: &[&str]) -> PathBuf {
+        let legacy_dir = dir.path().join(".config/.timers");
+        fs::create_dir_all(&legacy_dir).unwrap();
+
+        for file in files {
+            fs::write(legacy_dir.join(file), file).unwrap();
+        }
+
+        legacy_dir
+    }
+
+    #[test]
+    fn moves_config_and_database_with_sidecars() {
+        let dir = tempfile::tempdir().unwrap();
+        let legacy_dir = legacy(&dir, &["config.toml", "db.sqlite", "db.sqlite-wal"]);
+        let config_path = dir.path().join("config/timers/config.toml");
+        let db_path = dir.path().join("data/timers/db.sqlite");
+
+        let moved = move_legacy_files(&legacy_dir, Some(&config_path), Some(&db_path)).unwrap();
+
+        assert_eq!(moved.len(), 3);
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), "config.toml");
+        assert_eq!(fs::read_to_string(&db_path).unwrap(), "db.sqlite");
+        assert_eq!(
+            fs::read_to_string(dir.path().join("data/timers/db.sqlite-wal")).unwrap(),
+            "db.sqlite-wal"
+        );
+        assert!(!legacy_dir.exists());
+    }
+
+    #[test]
+    fn never_overwrites_existing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let legacy_dir = legacy(&dir, &["config.toml", "db.sqlite", "notes.txt"]);
+        let config_path = dir.path().join("config.toml");
+        fs::write(&config_path, "newer").unwrap();
+
+        let moved = move_legacy_files(&legacy_dir, Some(&config_path), None).unwrap();
+
+        assert!(moved.is_empty());
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), "newer");
+        assert!(legacy_dir.join("config.toml").exists());
+        assert!(legacy_dir.join("db.sqlite").exists());
+    }
+
+    #[test]
+    fn portable_mode_needs_the_marker_next_to_the_executable() {
+        let dir = tempfile::tempdir().unwrap();
+        let executable = dir.path().join("timers");
+        fs::write(&executable, "").unwrap();
+
+        assert_eq!(portable_root_of(&executable), None);
+
+        fs::write(dir.path().join(PORTABLE_MARKER), "").unwrap();
+        let root = portable_root_of(&executable).unwrap();
+        let directories = AppDirectories::portable(&root);
+
+        assert_eq!(directories.config_path(), dir.path().join("config.toml"));
+        assert_eq!(directories.db_path(), dir.path().join("db.sqlite"));
+        assert_eq!(directories.log_dir(), dir.path().join("logs"));
+        assert_eq!(directories.backup_dir(), dir.path().join("backups"));
+        assert_eq!(directories.export_dir(), dir.path().join("exports"));
+    }
+
+    #[test]
+    fn nothing_to_do_without_a_legacy_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join(".config/.timers");
+
+        let moved = move_legacy_files(&missing, Some(&dir.path().join("config.toml")), None);
+
+        assert!(moved.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,199 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+use ts_rs::TS;
+
+use crate::clock::Calendar;
+
+// What starting a timer does when another one is still running.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum ActiveTimerPolicy {
+    #[default]
+    StopPrevious,
+    Reject,
+}
+
+// What adding an entry by hand does when its range covers time already tracked.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum OverlapPolicy {
+    #[default]
+    Reject,
+    // Shortens the new entry to the free time, as long as that is a single stretch
+    Trim,
+    // Adds one entry for every stretch of free time in the range
+    Split,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(default)]
+#[ts(export)]
+pub struct TimerSettings {
+    pub active_timer_policy: ActiveTimerPolicy,
+    pub overlap_policy: OverlapPolicy,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(default)]
+#[ts(export)]
+pub struct PomodoroSettings {
+    pub work_minutes: u32,
+    pub short_break_minutes: u32,
+    pub long_break_minutes: u32,
+    // Work sessions in a set, the last one is followed by a long break
+    pub cycles_before_long_break: u32,
+}
+
+impl Default for PomodoroSettings {
+    fn default() -> Self {
+        PomodoroSettings {
+            work_minutes: 25,
+            short_break_minutes: 5,
+            long_break_minutes: 15,
+            cycles_before_long_break: 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(default)]
+#[ts(export)]
+pub struct NotificationSettings {
+    pub timer_started: bool,
+    pub timer_finished: bool,
+    pub timer_cancelled: bool,
+    pub breaks: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        NotificationSettings {
+            timer_started: true,
+            timer_finished: true,
+            timer_cancelled: true,
+            breaks: true,
+        }
+    }
+}
+
+// Which zone decides the day of an entry recorded somewhere else, e.g. before a flight.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum DayGrouping {
+    // The zone the entry was started in, so past days keep their shape
+    #[default]
+    EntryZone,
+    // The zone configured now
+    CurrentZone,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(default)]
+#[ts(export)]
+pub struct DaySettings {
+    // Hour at which a new day starts for statistics, for people working past midnight
+    pub start_hour: u32,
+    // IANA zone name, the system zone when not set
+    pub timezone: Option<String>,
+    pub group_by: DayGrouping,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageSettings {
+    // Relative paths are resolved against the directory of the configuration file
+    pub database_path: Option<PathBuf>,
+}
+
+// Contents of `config.toml`. Every section and key is optional, missing ones keep their
+// defaults.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigFile {
+    pub timers: TimerSettings,
+    pub pomodoro: PomodoroSettings,
+    pub notifications: NotificationSettings,
+    pub day: DaySettings,
+    pub storage: StorageSettings,
+}
+
+#[derive(Debug)]
+pub enum ConfigurationError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigurationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigurationError::Io(path, e) => {
+                write!(f, "Could not read configuration file {:?}: {}", path, e)
+            }
+            ConfigurationError::Parse(path, e) => {
+                write!(f, "Invalid configuration file {:?}: {}", path, e)
+            }
+            ConfigurationError::Invalid(message) => write!(f, "Invalid configuration: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigurationError {}
+
+impl ConfigFile {
+    // Parses the file, returning the keys that were not recognised alongside it so they can be
+    // reported instead of silently ignored.
+    pub fn parse(contents: &str) -> Result<(Self, Vec<String>), toml::de::Error> {
+        let mut unknown_keys = Vec::new();
+        let file = serde_ignored::deserialize(toml::Deserializer::new(contents), |path| {
+            unknown_keys.push(path.to_string())
+        })?;
+
+        Ok((file, unknown_keys))
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigurationError> {
+        let pomodoro = [
+            ("pomodoro.work_minutes", self.pomodoro.work_minutes),
+            (
+                "pomodoro.short_break_minutes",
+                self.pomodoro.short_break_minutes,
+            ),
+            (
+                "pomodoro.long_break_minutes",
+                self.pomodoro.long_break_minutes,
+            ),
+            (
+                "pomodoro.cycles_before_long_break",
+                self.pomodoro.cycles_before_long_break,
+            ),
+        ];
+
+        for (key, value) in pomodoro {
+            if value == 0 {
+                return Err(ConfigurationError::Invalid(format!(
+                    "{} must be greater than 0",
+                    key
+                )));
+            }
+        }
+
+        if self.day.start_hour > 23 {
+            return Err(ConfigurationError::Invalid(format!(
+                "day.start_hour must be between 0 and 23, got {}",
+                self.day.start_hour
+            )));
+        }
+
+        if let Some(timezone) = &self.day.timezone {
+            Calendar::from_settings(Some(timezone), self.day.start_hour)
+                .map_err(|e| ConfigurationError::Invalid(format!("day.timezone: {}", e)))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use notify::{EventKind, RecursiveMode, Watcher};
+use notify_rust::Notification;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use super::{publish, ConfigurationState};
+use crate::errors::TimersError;
+
+// Editors save in several steps (temporary file, rename, chmod), wait for them to settle
+// before reading the file.
+const SETTLE_TIME: Duration = Duration::from_millis(250);
+
+// Reloads the configuration whenever the file changes on disk. The parent directory is
+// watched rather than the file, so editors that replace the file keep being followed.
+pub fn watch(app: AppHandle) {
+    let config_path = app.state::<ConfigurationState>().get().config_path;
+
+    thread::spawn(move || {
+        if let Err(e) = run(&app, &config_path) {
+            log::error!(
+                "Could not watch configuration file {:?}: {}",
+                config_path,
+                e
+            );
+        }
+    });
+}
+
+fn run(app: &AppHandle, config_path: &Path) -> notify::Result<()> {
+    let (sender, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+
+    let directory = match config_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    watcher.watch(&directory, RecursiveMode::NonRecursive)?;
+    log::info!("Watching configuration file {:?}", config_path);
+
+    while let Ok(event) = events.recv() {
+        match event {
+            Ok(event) if touches(&event, config_path) => {}
+            Ok(_) => continue,
+            Err(e) => {
+                log::error!("Configuration watcher error: {}", e);
+                continue;
+            }
+        }
+
+        while events.recv_timeout(SETTLE_TIME).is_ok() {}
+        reload(app);
+    }
+
+    Ok(())
+}
+
+fn touches(event: &notify::Event, config_path: &Path) -> bool {
+    !matches!(event.kind, EventKind::Access(_))
+        && event
+            .paths
+            .iter()
+            .any(|path| path.file_name() == config_path.file_name())
+}
+
+// Invalid edits are reported and leave the last good configuration running
+fn reload(app: &AppHandle) {
+    let current = app.state::<ConfigurationState>().get();
+
+    match current.reload() {
+        Ok(reloaded) if reloaded == current => {}
+        Ok(reloaded) => {
+            for warning in &reloaded.warnings {
+                log::warn!("{}", warning);
+            }
+
+            publish(app, reloaded);
+            log::info!("Configuration reloaded from {:?}", current.config_path);
+        }
+        Err(e) => {
+            log::error!("Ignoring configuration change: {}", e);
+
+            if let Err(e) = send_invalid_configuration_notification(&e.to_string()) {
+                log::error!("{}", e);
+            }
+        }
+    }
+}
+
+fn send_invalid_configuration_notification(message: &str) -> Result<(), TimersError> {
+    Notification::new()
+        .summary("H.Q.! Configuration Not Applied")
+        .body(message)
+        .show()?;
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use serde::{Serialize, Serializer};
+use std::fmt;
+use ts_rs::TS;
+
+use crate::configuration::ConfigurationError;
+
+// Error returned by every Tauri command. It is serialized as `{ code, message }` so the
+// frontend can branch on `code` and show `message` to the user.
+#[derive(Debug)]
+pub enum TimersError {
+    Storage(rusqlite::Error),
+    Pool(r2d2::Error),
+    NotFound(String),
+    Validation(String),
+    Notification(String),
+    Configuration(ConfigurationError),
+}
+
+// Shape of a `TimersError` once it reaches the frontend.
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct ErrorPayload {
+    pub code: String,
+    pub message: String,
+}
+
+impl TimersError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            TimersError::Storage(_) => "storage",
+            TimersError::Pool(_) => "pool",
+            TimersError::NotFound(_) => "not_found",
+            TimersError::Validation(_) => "validation",
+            TimersError::Notification(_) => "notification",
+            TimersError::Configuration(_) => "configuration",
+        }
+    }
+}
+
+impl fmt::Display for TimersError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimersError::Storage(e) => write!(f, "Storage error: {}", e),
+            TimersError::Pool(e) => write!(f, "Could not get a database connection: {}", e),
+            TimersError::NotFound(what) => write!(f, "{} not found", what),
+            TimersError::Validation(message) => write!(f, "{}", message),
+            TimersError::Notification(message) => {
+                write!(f, "Could not send notification: {}", message)
+            }
+            TimersError::Configuration(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for TimersError {}
+
+impl Serialize for TimersError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        ErrorPayload {
+            code: self.code().to_string(),
+            message: self.to_string(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl From<rusqlite::Error> for TimersError {
+    fn from(e: rusqlite::Error) -> Self {
+        TimersError::Storage(e)
+    }
+}
+
+impl From<r2d2::Error> for TimersError {
+    fn from(e: r2d2::Error) -> Self {
+        TimersError::Pool(e)
+    }
+}
+
+impl From<notify_rust::error::Error> for TimersError {
+    fn from(e: notify_rust::error::Error) -> Self {
+        TimersError::Notification(e.to_string())
+    }
+}
+
+// Invalid settings are the caller's fault, anything else means the file could not be handled
+impl From<ConfigurationError> for TimersError {
+    fn from(e: ConfigurationError) -> Self {
+        match e {
+            ConfigurationError::Invalid(message) => TimersError::Validation(message),
+            e => TimersError::Configuration(e),
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use chrono::Local;
+use env_logger::{Builder, Target};
+use log::LevelFilter;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+// Sends every record to the terminal and, when there is one, to the log file
+struct Tee {
+    file: Option<File>,
+}
+
+impl Write for Tee {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(file) = &mut self.file {
+            // A full disk shouldn't take the terminal output down with it
+            let _ = file.write_all(buf);
+        }
+
+        io::stderr().write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(file) = &mut self.file {
+            let _ = file.flush();
+        }
+
+        io::stderr().flush()
+    }
+}
+
+pub fn init(development_mode: bool, log_dir: Option<&Path>) {
+    let file = log_dir.and_then(|dir| match open_log_file(dir) {
+        Ok(file) => Some(file),
+        Err(e) => {
+            eprintln!("Could not open log file in {:?}: {}", dir, e);
+            None
+        }
+    });
+
+    let mut builder = Builder::new();
+
+    if development_mode {
+        builder
+            .format(|buf, record| {
+                writeln!(
+                    buf,
+                    "{} [{}] - {}:{} - {}",
+                    Local::now().format("%Y-%m-%dT%H:%M:%S"),
+                    record.level(),
+                    record.file().unwrap_or_default(),
+                    record.line().unwrap_or_default(),
+                    record.args()
+                )
+            })
+            .filter(None, LevelFilter::Debug);
+    } else {
+        builder
+            .format(|buf, record| {
+                writeln!(
+                    buf,
+                    "{} - {}",
+                    Local::now().format("%Y-%m-%dT%H:%M:%S"),
+                    record.args()
+                )
+            })
+            .filter(None, LevelFilter::Info);
+    }
+
+    builder.target(Target::Pipe(Box::new(Tee { file }))).init();
+}
+
+fn open_log_file(log_dir: &Path) -> io::Result<File> {
+    fs::create_dir_all(log_dir)?;
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_dir.join("timers.log"))
+}
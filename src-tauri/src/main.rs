@@ -5,7 +5,12 @@ extern crate r2d2;
 extern crate r2d2_sqlite;
 extern crate rusqlite;
 
+use tauri::Manager;
+
+mod clock;
 mod configuration;
+mod errors;
+mod logging;
 mod storage;
 mod timers;
 
@@ -16,31 +21,91 @@ fn greet(name: &str) -> String {
 }
 
 fn main() {
-    let configuration = configuration::Configuration::load();
-    plogger::init(configuration.development_mode);
+    let configuration = configuration::Configuration::load().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    logging::init(
+        configuration.development_mode,
+        configuration.log_dir.as_deref(),
+    );
     log::info!("Running Timers!");
+
+    if configuration.portable {
+        log::info!("Running in portable mode");
+    }
     log::debug!("{:?}", &configuration);
 
+    for warning in &configuration.warnings {
+        log::warn!("{}", warning);
+    }
+
+    for (from, to) in &configuration.moved_files {
+        log::info!("Moved {:?} to {:?}", from, to);
+    }
+
     let db_pool = storage::setup_database(&configuration).expect("Could not set up database.");
+    let clock = clock::OffsetClock::default();
+
+    match timers::repair_open_entries(&db_pool, &clock, configuration.calendar()) {
+        Ok(0) => {}
+        Ok(repaired) => log::info!("Closed {} stale open time entries", repaired),
+        Err(e) => log::error!("Could not repair open time entries: {}", e),
+    }
 
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .manage(db_pool)
-        .manage(configuration)
+        .manage(configuration::ConfigurationState::new(configuration))
+        .manage(clock)
+        .setup(|app| {
+            app.manage(timers::PomodoroScheduler::start(app.handle().clone()));
+            configuration::watch(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
+            clock::time_travel_command,
             configuration::load_configuration_command,
+            configuration::update_configuration_command,
             timers::start_timer_command,
             timers::start_pomodoro_timer_command,
             timers::finish_timer_command,
             timers::cancel_timer_command,
+            timers::discard_timer_command,
+            timers::pause_timer_command,
+            timers::resume_timer_command,
             timers::load_daily_statistics_command,
             timers::load_daily_history_command,
             timers::load_statistics_history_command,
             timers::get_active_timer_command,
+            timers::get_pomodoro_phase_command,
             timers::load_timer_entries_history_command,
             timers::update_time_entry_activity_command,
+            timers::update_time_entry_command,
+            timers::create_time_entry_command,
+            timers::split_time_entry_command,
+            timers::merge_time_entries_command,
+            timers::delete_time_entry_command,
+            timers::restore_time_entry_command,
+            timers::load_trash_command,
+            timers::purge_time_entries_command,
             timers::load_activity_statistics_for_date_command,
+            timers::load_tag_statistics_for_date_command,
+            timers::load_tags_command,
+            timers::load_catalogue_command,
+            timers::rename_catalogue_item_command,
+            timers::merge_catalogue_items_command,
+            timers::archive_catalogue_item_command,
+            timers::load_clients_command,
+            timers::create_client_command,
+            timers::update_client_command,
+            timers::load_projects_command,
+            timers::create_project_command,
+            timers::update_project_command,
+            timers::load_activity_billing_command,
+            timers::assign_activity_command,
+            timers::load_earnings_command,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
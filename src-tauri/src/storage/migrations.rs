@@ -0,0 +1,487 @@
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension};
+use std::fmt;
+
+pub struct Migration {
+    pub id: i64,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+// Ordered list of every schema change. Append new migrations at the end with the next id,
+// never edit one that has already shipped.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        id: 1,
+        name: "create_timer_statistics_and_timers",
+        // Uses IF NOT EXISTS so databases created before migrations were tracked (v0) adopt it as-is
+        sql: "
+        CREATE TABLE IF NOT EXISTS timer_statistics (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date_string TEXT NOT NULL,
+            timers_started INTEGER NOT NULL,
+            timers_finished INTEGER NOT NULL,
+            timers_cancelled INTEGER NOT NULL,
+            created_at DATETIME NOT NULL,
+            updated_at DATETIME NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS timers (
+            id INTEGER PRIMARY KEY,
+            activity TEXT DEFAULT NULL,
+            area TEXT DEFAULT NULL,
+            start_time DATETIME NOT NULL,
+            end_time DATETIME DEFAULT NULL,
+            duration INTEGER NOT NULL DEFAULT 0,
+            is_pomodoro BOOLEAN,
+            created_at DATETIME NOT NULL,
+            updated_at DATETIME NOT NULL
+        );",
+    },
+    Migration {
+        id: 2,
+        name: "create_timer_pauses",
+        sql: "
+        CREATE TABLE timer_pauses (
+            id INTEGER PRIMARY KEY,
+            timer_id INTEGER NOT NULL REFERENCES timers(id) ON DELETE CASCADE,
+            started_at DATETIME NOT NULL,
+            ended_at DATETIME DEFAULT NULL,
+            created_at DATETIME NOT NULL,
+            updated_at DATETIME NOT NULL
+        );
+
+        CREATE INDEX timer_pauses_timer_id ON timer_pauses (timer_id);",
+    },
+    Migration {
+        id: 3,
+        name: "create_pomodoro_phases",
+        sql: "
+        CREATE TABLE pomodoro_phases (
+            id INTEGER PRIMARY KEY,
+            kind TEXT NOT NULL,
+            cycle INTEGER NOT NULL,
+            timer_id INTEGER DEFAULT NULL REFERENCES timers(id) ON DELETE SET NULL,
+            started_at DATETIME NOT NULL,
+            ends_at DATETIME NOT NULL,
+            ended_at DATETIME DEFAULT NULL,
+            created_at DATETIME NOT NULL,
+            updated_at DATETIME NOT NULL
+        );",
+    },
+    Migration {
+        id: 4,
+        name: "add_timer_zones",
+        sql: "
+        ALTER TABLE timers ADD COLUMN utc_offset INTEGER DEFAULT NULL;
+        ALTER TABLE timers ADD COLUMN timezone TEXT DEFAULT NULL;",
+    },
+    Migration {
+        id: 5,
+        name: "add_timer_is_manual",
+        sql: "ALTER TABLE timers ADD COLUMN is_manual BOOLEAN NOT NULL DEFAULT 0;",
+    },
+    Migration {
+        id: 6,
+        name: "add_timer_deleted_at",
+        sql: "ALTER TABLE timers ADD COLUMN deleted_at DATETIME DEFAULT NULL;",
+    },
+    Migration {
+        id: 7,
+        name: "add_timer_status",
+        // Cancelled timers were only counted, not marked, so every ended timer is backfilled as
+        // finished
+        sql: "
+        ALTER TABLE timers ADD COLUMN status TEXT NOT NULL DEFAULT 'finished';
+
+        UPDATE timers SET status = CASE
+            WHEN end_time IS NOT NULL THEN 'finished'
+            WHEN EXISTS (SELECT 1 FROM timer_pauses p WHERE p.timer_id = timers.id AND p.ended_at IS NULL) THEN 'paused'
+            ELSE 'running'
+        END;",
+    },
+    Migration {
+        id: 8,
+        name: "create_tags",
+        sql: "
+        CREATE TABLE tags (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            created_at DATETIME NOT NULL
+        );
+
+        CREATE TABLE timer_tags (
+            timer_id INTEGER NOT NULL REFERENCES timers(id) ON DELETE CASCADE,
+            tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+            PRIMARY KEY (timer_id, tag_id)
+        );
+
+        CREATE INDEX timer_tags_tag_id ON timer_tags (tag_id);",
+    },
+    Migration {
+        id: 9,
+        name: "create_activities_and_areas",
+        // Names typed in different case end up in one catalogue entry, spelled the way they were
+        // first used
+        sql: "
+        CREATE TABLE activities (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            archived_at DATETIME DEFAULT NULL,
+            created_at DATETIME NOT NULL,
+            updated_at DATETIME NOT NULL
+        );
+
+        CREATE TABLE areas (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            archived_at DATETIME DEFAULT NULL,
+            created_at DATETIME NOT NULL,
+            updated_at DATETIME NOT NULL
+        );
+
+        INSERT OR IGNORE INTO activities (name, created_at, updated_at)
+            SELECT TRIM(activity), MIN(created_at), MAX(updated_at) FROM timers
+            WHERE TRIM(activity) != ''
+            GROUP BY TRIM(activity) ORDER BY MIN(created_at);
+
+        INSERT OR IGNORE INTO areas (name, created_at, updated_at)
+            SELECT TRIM(area), MIN(created_at), MAX(updated_at) FROM timers
+            WHERE TRIM(area) != ''
+            GROUP BY TRIM(area) ORDER BY MIN(created_at);
+
+        ALTER TABLE timers ADD COLUMN activity_id INTEGER DEFAULT NULL REFERENCES activities(id);
+        ALTER TABLE timers ADD COLUMN area_id INTEGER DEFAULT NULL REFERENCES areas(id);
+
+        UPDATE timers SET
+            activity_id = (SELECT id FROM activities WHERE name = TRIM(timers.activity)),
+            area_id = (SELECT id FROM areas WHERE name = TRIM(timers.area));
+
+        ALTER TABLE timers DROP COLUMN activity;
+        ALTER TABLE timers DROP COLUMN area;
+
+        CREATE INDEX timers_activity_id ON timers (activity_id);
+        CREATE INDEX timers_area_id ON timers (area_id);",
+    },
+    Migration {
+        id: 10,
+        name: "create_clients_and_projects",
+        // Billing columns left NULL are inherited from the level above: activity, project, client.
+        // Entries whose activity has no project belong to the default project.
+        sql: "
+        CREATE TABLE clients (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            billable BOOLEAN DEFAULT NULL,
+            currency TEXT DEFAULT NULL,
+            hourly_rate INTEGER DEFAULT NULL,
+            created_at DATETIME NOT NULL,
+            updated_at DATETIME NOT NULL
+        );
+
+        CREATE TABLE projects (
+            id INTEGER PRIMARY KEY,
+            client_id INTEGER DEFAULT NULL REFERENCES clients(id),
+            name TEXT NOT NULL COLLATE NOCASE,
+            is_default BOOLEAN NOT NULL DEFAULT 0,
+            billable BOOLEAN DEFAULT NULL,
+            currency TEXT DEFAULT NULL,
+            hourly_rate INTEGER DEFAULT NULL,
+            created_at DATETIME NOT NULL,
+            updated_at DATETIME NOT NULL
+        );
+
+        CREATE UNIQUE INDEX projects_is_default ON projects (is_default) WHERE is_default;
+
+        INSERT INTO projects (name, is_default, created_at, updated_at)
+            VALUES (
+                'No project', 1,
+                strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now'), strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now')
+            );
+
+        ALTER TABLE activities ADD COLUMN project_id INTEGER DEFAULT NULL REFERENCES projects(id);
+        ALTER TABLE activities ADD COLUMN billable BOOLEAN DEFAULT NULL;
+        ALTER TABLE activities ADD COLUMN currency TEXT DEFAULT NULL;
+        ALTER TABLE activities ADD COLUMN hourly_rate INTEGER DEFAULT NULL;",
+    },
+];
+
+#[derive(Debug)]
+pub enum MigrationError {
+    Sqlite(rusqlite::Error),
+    DatabaseTooNew {
+        database_version: i64,
+        supported_version: i64,
+    },
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::Sqlite(e) => write!(f, "Migration failed: {}", e),
+            MigrationError::DatabaseTooNew {
+                database_version,
+                supported_version,
+            } => write!(
+                f,
+                "Database schema version {} is newer than the latest version supported by this build ({})",
+                database_version, supported_version
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+impl From<rusqlite::Error> for MigrationError {
+    fn from(e: rusqlite::Error) -> Self {
+        MigrationError::Sqlite(e)
+    }
+}
+
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map_or(0, |m| m.id)
+}
+
+pub fn current_version(conn: &Connection) -> rusqlite::Result<i64> {
+    let version: Option<i64> = conn
+        .query_row("SELECT MAX(id) FROM migrations", [], |row| row.get(0))
+        .optional()?
+        .flatten();
+
+    Ok(version.unwrap_or(0))
+}
+
+pub fn run(conn: &mut Connection) -> Result<i64, MigrationError> {
+    run_migrations(conn, MIGRATIONS)
+}
+
+fn run_migrations(conn: &mut Connection, migrations: &[Migration]) -> Result<i64, MigrationError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS migrations (
+            id INTEGER PRIMARY KEY,
+            migrated_at DATETIME NOT NULL
+        );",
+        [],
+    )?;
+
+    let supported_version = migrations.last().map_or(0, |m| m.id);
+    let database_version = current_version(conn)?;
+
+    if database_version > supported_version {
+        return Err(MigrationError::DatabaseTooNew {
+            database_version,
+            supported_version,
+        });
+    }
+
+    for migration in migrations.iter().filter(|m| m.id > database_version) {
+        log::info!("Applying migration {} ({})", migration.id, migration.name);
+
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration.sql)?;
+        tx.execute(
+            "INSERT INTO migrations (id, migrated_at) VALUES (?1, ?2)",
+            rusqlite::params![migration.id, Utc::now().to_rfc3339()],
+        )?;
+        tx.commit()?;
+    }
+
+    current_version(conn).map_err(MigrationError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table_columns(conn: &Connection, table: &str) -> Vec<String> {
+        let mut stmt = conn
+            .prepare(&format!("PRAGMA table_info({})", table))
+            .unwrap();
+        let rows = stmt.query_map([], |row| row.get(1)).unwrap();
+
+        rows.map(|r| r.unwrap()).collect()
+    }
+
+    fn tables(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")
+            .unwrap();
+        let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+
+        rows.map(|r| r.unwrap()).collect()
+    }
+
+    fn applied_migrations(conn: &Connection) -> Vec<i64> {
+        let mut stmt = conn
+            .prepare("SELECT id FROM migrations ORDER BY id")
+            .unwrap();
+        let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+
+        rows.map(|r| r.unwrap()).collect()
+    }
+
+    fn v0_database() -> (tempfile::TempDir, Connection) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("v0.db");
+        std::fs::copy(concat!(env!("CARGO_MANIFEST_DIR"), "/file.db"), &path).unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        (dir, conn)
+    }
+
+    #[test]
+    fn migrates_empty_database_to_latest() {
+        let mut conn = Connection::open_in_memory().unwrap();
+
+        assert_eq!(run(&mut conn).unwrap(), latest_version());
+        assert_eq!(
+            applied_migrations(&conn),
+            MIGRATIONS.iter().map(|m| m.id).collect::<Vec<_>>()
+        );
+        assert!(!table_columns(&conn, "timers").is_empty());
+        assert!(!table_columns(&conn, "timer_statistics").is_empty());
+    }
+
+    #[test]
+    fn running_twice_is_a_no_op() {
+        let mut conn = Connection::open_in_memory().unwrap();
+
+        run(&mut conn).unwrap();
+        run(&mut conn).unwrap();
+
+        assert_eq!(applied_migrations(&conn).len(), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn upgrades_v0_database_and_keeps_data() {
+        let (_dir, mut conn) = v0_database();
+        assert_eq!(current_version(&conn).unwrap(), 0);
+
+        let timers_before: i64 = conn
+            .query_row("SELECT COUNT(*) FROM timers", [], |row| row.get(0))
+            .unwrap();
+
+        assert_eq!(run(&mut conn).unwrap(), latest_version());
+
+        let timers_after: i64 = conn
+            .query_row("SELECT COUNT(*) FROM timers", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(timers_before, timers_after);
+
+        let ended_not_finished: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM timers WHERE end_time IS NOT NULL AND status != 'finished'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(ended_not_finished, 0);
+
+        let mut fresh = Connection::open_in_memory().unwrap();
+        run(&mut fresh).unwrap();
+        assert_eq!(tables(&conn), tables(&fresh));
+        for table in tables(&fresh) {
+            assert_eq!(
+                table_columns(&conn, &table),
+                table_columns(&fresh, &table),
+                "columns of {} differ",
+                table
+            );
+        }
+    }
+
+    #[test]
+    fn activity_and_area_names_move_into_catalogues() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let before_catalogues = MIGRATIONS.iter().position(|m| m.id == 9).unwrap();
+        run_migrations(&mut conn, &MIGRATIONS[..before_catalogues]).unwrap();
+
+        // The first spelling used is the one kept
+        let rows = [
+            (Some("Review"), Some("Work")),
+            (Some("review "), None),
+            (Some("Reading"), Some("")),
+            (None, Some("work")),
+        ];
+        for (minute, (activity, area)) in rows.iter().enumerate() {
+            let created_at = format!("2024-07-16T09:0{}:00+00:00", minute);
+            conn.execute(
+                "INSERT INTO timers (activity, area, start_time, created_at, updated_at)
+                VALUES (?1, ?2, ?3, ?3, ?3)",
+                rusqlite::params![activity, area, created_at],
+            )
+            .unwrap();
+        }
+
+        run(&mut conn).unwrap();
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT a.name, ar.name FROM timers
+                LEFT JOIN activities a ON a.id = timers.activity_id
+                LEFT JOIN areas ar ON ar.id = timers.area_id
+                ORDER BY timers.id",
+            )
+            .unwrap();
+        let names: Vec<(Option<String>, Option<String>)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+
+        let name = |n: &str| Some(n.to_string());
+        assert_eq!(
+            names,
+            vec![
+                (name("Review"), name("Work")),
+                (name("Review"), None),
+                (name("Reading"), None),
+                (None, name("Work")),
+            ]
+        );
+    }
+
+    #[test]
+    fn refuses_database_newer_than_binary() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run(&mut conn).unwrap();
+        conn.execute(
+            "INSERT INTO migrations (id, migrated_at) VALUES (?1, ?2)",
+            rusqlite::params![latest_version() + 1, Utc::now().to_rfc3339()],
+        )
+        .unwrap();
+
+        match run(&mut conn) {
+            Err(MigrationError::DatabaseTooNew {
+                database_version,
+                supported_version,
+            }) => {
+                assert_eq!(database_version, latest_version() + 1);
+                assert_eq!(supported_version, latest_version());
+            }
+            other => panic!("Expected DatabaseTooNew, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn failed_migration_is_rolled_back() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let migrations = [
+            Migration {
+                id: 1,
+                name: "create_table",
+                sql: "CREATE TABLE things (id INTEGER PRIMARY KEY);",
+            },
+            Migration {
+                id: 2,
+                name: "broken",
+                sql: "ALTER TABLE things ADD COLUMN name TEXT; SELECT * FROM missing_table;",
+            },
+        ];
+
+        assert!(run_migrations(&mut conn, &migrations).is_err());
+        assert_eq!(applied_migrations(&conn), vec![1]);
+        assert_eq!(table_columns(&conn, "things"), vec!["id"]);
+    }
+}
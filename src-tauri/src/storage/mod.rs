@@ -1,17 +1,27 @@
-use log;
+use chrono::{DateTime, Utc};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::Connection;
+
+pub mod migrations;
 
 pub fn setup_database(
     configuration: &super::configuration::Configuration,
 ) -> Result<Pool<SqliteConnectionManager>, String> {
     log::debug!("Initializing db {:?}", &configuration.db_path);
-    let manager = SqliteConnectionManager::file(std::path::PathBuf::from(&configuration.db_path));
+    if let Some(parent) = configuration.db_path.parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Could not create database directory: {}", e))?;
+        }
+    }
+    let manager = SqliteConnectionManager::file(std::path::PathBuf::from(&configuration.db_path))
+        .with_init(init_connection);
     log::debug!("DB Was initialized");
 
     match r2d2::Pool::new(manager) {
         Ok(pool) => {
-            setup_structure(&pool, configuration).unwrap();
+            setup_structure(&pool)?;
             log::debug!("Pool Was initialized");
             Ok(pool)
         }
@@ -22,63 +32,64 @@ pub fn setup_database(
     }
 }
 
-// TODO: Manage db versions
-pub fn setup_structure(
-    pool: &Pool<SqliteConnectionManager>,
-    configuration: &super::configuration::Configuration,
-) -> rusqlite::Result<()> {
-    // if configuration.development_mode {
-    //     log::debug!("H.Q.! is running in development mode, not executing migrations");
-    //     return Ok(());
-    // }
-
-    pool.get()
-        .unwrap()
-        .execute(
-            "CREATE TABLE IF NOT EXISTS migrations (
-                id INTEGER PRIMARY KEY,
-                migrated_at DATETIME NOT NULL
-            );",
-            [],
-        )
-        .unwrap();
+// SQLite leaves foreign keys off on every new connection, so the cascades declared in the schema
+// only fire once this has run
+pub fn init_connection(conn: &mut Connection) -> rusqlite::Result<()> {
+    conn.execute_batch("PRAGMA foreign_keys = ON;")
+}
 
-    pool.get()
-        .unwrap()
-        .execute(
-            "
-            CREATE TABLE IF NOT EXISTS timer_statistics (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                date_string TEXT NOT NULL,
-                timers_started INTEGER NOT NULL,
-                timers_finished INTEGER NOT NULL,
-                timers_cancelled INTEGER NOT NULL,
-                created_at DATETIME NOT NULL,
-                updated_at DATETIME NOT NULL
-            );",
-            [],
-        )
-        .unwrap();
+pub fn setup_structure(pool: &Pool<SqliteConnectionManager>) -> Result<(), String> {
+    let mut connection = pool.get().map_err(|e| {
+        log::error!("Could not get connection to run migrations: {:?}", e);
+        String::from("Could not initialize database")
+    })?;
 
-    pool.get()
-        .unwrap()
-        .execute(
-            "
-            CREATE TABLE IF NOT EXISTS timers (
-                id INTEGER PRIMARY KEY,
-                activity TEXT DEFAULT NULL,
-                area TEXT DEFAULT NULL,
-                start_time DATETIME NOT NULL,
-                end_time DATETIME DEFAULT NULL,
-                duration INTEGER NOT NULL DEFAULT 0,
-                is_pomodoro BOOLEAN,
-                created_at DATETIME NOT NULL,
-                updated_at DATETIME NOT NULL
+    match migrations::run(&mut connection) {
+        Ok(version) => {
+            log::debug!(
+                "Database schema is at version {} (latest {})",
+                version,
+                migrations::latest_version()
             );
-           ",
-            [],
-        )
-        .unwrap();
+            Ok(())
+        }
+        Err(e) => {
+            log::error!("Could not migrate db: {}", e);
+            Err(e.to_string())
+        }
+    }
+}
+
+// Timestamps are stored as RFC3339 strings, surface malformed values as a conversion error
+// instead of panicking while mapping a row.
+pub fn parse_datetime(index: usize, value: &str) -> rusqlite::Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|d| d.with_timezone(&Utc))
+        .map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(
+                index,
+                rusqlite::types::Type::Text,
+                Box::new(e),
+            )
+        })
+}
 
-    Ok(())
+pub fn get_datetime(row: &rusqlite::Row, column: &str) -> rusqlite::Result<DateTime<Utc>> {
+    let value: String = row.get(column)?;
+    parse_datetime(row.as_ref().column_index(column)?, &value)
+}
+
+pub fn get_optional_datetime(
+    row: &rusqlite::Row,
+    column: &str,
+) -> rusqlite::Result<Option<DateTime<Utc>>> {
+    let value: Option<String> = row.get(column)?;
+
+    match value {
+        Some(v) => Ok(Some(parse_datetime(
+            row.as_ref().column_index(column)?,
+            &v,
+        )?)),
+        None => Ok(None),
+    }
 }
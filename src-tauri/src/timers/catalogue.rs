@@ -0,0 +1,208 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, Result, Row};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::clock::Clock;
+use crate::storage::{get_datetime, get_optional_datetime};
+
+// The two lists of names entries are filed under. Both work the same way, each in its own table.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum CatalogueKind {
+    Activity,
+    Area,
+}
+
+impl CatalogueKind {
+    fn table(&self) -> &'static str {
+        match self {
+            CatalogueKind::Activity => "activities",
+            CatalogueKind::Area => "areas",
+        }
+    }
+
+    // Column of `timers` pointing at the catalogue
+    fn column(&self) -> &'static str {
+        match self {
+            CatalogueKind::Activity => "activity_id",
+            CatalogueKind::Area => "area_id",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CatalogueKind::Activity => "Activity",
+            CatalogueKind::Area => "Area",
+        }
+    }
+}
+
+// A named activity or area. Names are unique regardless of case. Archived ones are left out
+// of the list offered when starting a timer, but keep their history.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct CatalogueItem {
+    pub id: i32,
+    pub name: String,
+    pub archived_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl CatalogueItem {
+    // The item called `name`, added when it's new and brought back when it was archived.
+    // Returns `None` for no name or an empty one.
+    pub fn find_or_create(
+        conn: &Connection,
+        kind: CatalogueKind,
+        name: Option<String>,
+        clock: &dyn Clock,
+    ) -> Result<Option<Self>> {
+        let name = match name.as_deref().map(str::trim) {
+            Some(name) if !name.is_empty() => name,
+            _ => return Ok(None),
+        };
+        let now = clock.now().to_rfc3339();
+
+        conn.execute(
+            &format!(
+                "INSERT OR IGNORE INTO {} (name, created_at, updated_at) VALUES (?1, ?2, ?2)",
+                kind.table()
+            ),
+            rusqlite::params![name, now],
+        )?;
+        conn.execute(
+            &format!(
+                "UPDATE {} SET archived_at = NULL, updated_at = ?2
+                WHERE name = ?1 AND archived_at IS NOT NULL",
+                kind.table()
+            ),
+            rusqlite::params![name, now],
+        )?;
+
+        Self::find_by_name(conn, kind, name)
+    }
+
+    pub fn find(conn: &Connection, kind: CatalogueKind, id: i32) -> Result<Option<Self>> {
+        conn.query_row(
+            &format!("SELECT * FROM {} WHERE id = ?1", kind.table()),
+            rusqlite::params![id],
+            Self::from_row,
+        )
+        .optional()
+    }
+
+    pub fn find_by_name(
+        conn: &Connection,
+        kind: CatalogueKind,
+        name: &str,
+    ) -> Result<Option<Self>> {
+        conn.query_row(
+            &format!("SELECT * FROM {} WHERE name = ?1", kind.table()),
+            rusqlite::params![name],
+            Self::from_row,
+        )
+        .optional()
+    }
+
+    // Items by name, archived ones only on request
+    pub fn list(
+        conn: &Connection,
+        kind: CatalogueKind,
+        include_archived: bool,
+    ) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT * FROM {} WHERE ?1 OR archived_at IS NULL ORDER BY name",
+            kind.table()
+        ))?;
+        let rows = stmt.query_map(rusqlite::params![include_archived], Self::from_row)?;
+
+        let mut vec: Vec<CatalogueItem> = Vec::new();
+
+        for row in rows {
+            vec.push(row?);
+        }
+
+        Ok(vec)
+    }
+
+    // Entries only store the id, so the new name shows up everywhere at once
+    pub fn rename(
+        &mut self,
+        kind: CatalogueKind,
+        name: String,
+        conn: &Connection,
+        clock: &dyn Clock,
+    ) -> Result<()> {
+        let now = clock.now();
+        conn.execute(
+            &format!(
+                "UPDATE {} SET name = ?1, updated_at = ?2 WHERE id = ?3",
+                kind.table()
+            ),
+            rusqlite::params![name, now.to_rfc3339(), self.id],
+        )?;
+        self.name = name;
+        self.updated_at = now;
+        Ok(())
+    }
+
+    pub fn set_archived_at(
+        &mut self,
+        kind: CatalogueKind,
+        archived_at: Option<DateTime<Utc>>,
+        conn: &Connection,
+        clock: &dyn Clock,
+    ) -> Result<()> {
+        let now = clock.now();
+        conn.execute(
+            &format!(
+                "UPDATE {} SET archived_at = ?1, updated_at = ?2 WHERE id = ?3",
+                kind.table()
+            ),
+            rusqlite::params![
+                archived_at.map(|a| a.to_rfc3339()),
+                now.to_rfc3339(),
+                self.id
+            ],
+        )?;
+        self.archived_at = archived_at;
+        self.updated_at = now;
+        Ok(())
+    }
+
+    // Files every entry of this item, trashed ones included, under `target_id` and removes this
+    // item. Returns how many entries moved.
+    pub fn merge_into(
+        &self,
+        kind: CatalogueKind,
+        target_id: i32,
+        conn: &Connection,
+    ) -> Result<usize> {
+        let moved = conn.execute(
+            &format!(
+                "UPDATE timers SET {column} = ?1 WHERE {column} = ?2",
+                column = kind.column()
+            ),
+            rusqlite::params![target_id, self.id],
+        )?;
+        conn.execute(
+            &format!("DELETE FROM {} WHERE id = ?1", kind.table()),
+            rusqlite::params![self.id],
+        )?;
+
+        Ok(moved)
+    }
+
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            archived_at: get_optional_datetime(row, "archived_at")?,
+            created_at: get_datetime(row, "created_at")?,
+            updated_at: get_datetime(row, "updated_at")?,
+        })
+    }
+}
@@ -1,198 +1,649 @@
 use std::collections::HashMap;
 
-use chrono::{Date, DateTime, Duration, NaiveDate, Utc};
-use rusqlite::{Connection, OptionalExtension, Result, Row, Statement};
-use serde::Serialize;
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use rusqlite::{Connection, OptionalExtension, Result, Row};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
 
-#[derive(Debug, Serialize)]
+use super::catalogue::{CatalogueItem, CatalogueKind};
+use super::pauses::TimerPause;
+use super::tags;
+use crate::clock::{Calendar, Clock};
+use crate::storage::{get_datetime, get_optional_datetime};
+
+// Columns read by `from_row`. Pause data comes along so a running timer can report its active
+// duration without another query, and so do the tags and catalogue names.
+const COLUMNS: &str = "timers.*,
+    (SELECT name FROM activities a WHERE a.id = timers.activity_id) AS activity,
+    (SELECT name FROM areas ar WHERE ar.id = timers.area_id) AS area,
+    (SELECT p.id FROM projects p
+        WHERE p.id = (SELECT a.project_id FROM activities a WHERE a.id = timers.activity_id) OR p.is_default
+        ORDER BY p.is_default LIMIT 1) AS project_id,
+    (SELECT p.name FROM projects p
+        WHERE p.id = (SELECT a.project_id FROM activities a WHERE a.id = timers.activity_id) OR p.is_default
+        ORDER BY p.is_default LIMIT 1) AS project,
+    (SELECT started_at FROM timer_pauses p WHERE p.timer_id = timers.id AND p.ended_at IS NULL) AS paused_at,
+    (SELECT COALESCE(SUM(MAX(0, unixepoch(p.ended_at) - MAX(unixepoch(p.started_at), unixepoch(timers.start_time)))), 0)
+        FROM timer_pauses p WHERE p.timer_id = timers.id AND p.ended_at IS NOT NULL) AS paused_seconds,
+    (SELECT group_concat(t.name, char(31)) FROM timer_tags tt JOIN tags t ON t.id = tt.tag_id
+        WHERE tt.timer_id = timers.id) AS tags";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum TimerStatus {
+    Running,
+    Paused,
+    Finished,
+    Cancelled,
+    // Thrown away right after starting, it never counts anywhere
+    Discarded,
+}
+
+impl TimerStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TimerStatus::Running => "running",
+            TimerStatus::Paused => "paused",
+            TimerStatus::Finished => "finished",
+            TimerStatus::Cancelled => "cancelled",
+            TimerStatus::Discarded => "discarded",
+        }
+    }
+}
+
+impl ToSql for TimerStatus {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for TimerStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "running" => Ok(TimerStatus::Running),
+            "paused" => Ok(TimerStatus::Paused),
+            "finished" => Ok(TimerStatus::Finished),
+            "cancelled" => Ok(TimerStatus::Cancelled),
+            "discarded" => Ok(TimerStatus::Discarded),
+            other => Err(FromSqlError::Other(
+                format!("Unknown timer status {}", other).into(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
 pub struct TimerEntry {
     pub id: i32,
+    // Catalogue ids, along with their current names
+    pub activity_id: Option<i32>,
     pub activity: Option<String>,
+    pub area_id: Option<i32>,
     pub area: Option<String>,
+    // Project of the activity, the default project when it has none
+    pub project_id: i32,
+    pub project: String,
+    // Sorted by name
+    pub tags: Vec<String>,
     pub start_time: DateTime<Utc>,
     pub end_time: Option<DateTime<Utc>>,
+    // Active time in seconds, pauses excluded
     pub duration: i32,
     pub is_pomodoro: bool,
+    pub status: TimerStatus,
+    // Entered by hand after the fact rather than timed
+    pub is_manual: bool,
+    // Set while the timer is paused
+    pub paused_at: Option<DateTime<Utc>>,
+    // Seconds east of UTC and IANA zone where the entry was started. Entries recorded before
+    // these were stored have neither.
+    pub utc_offset: Option<i32>,
+    pub timezone: Option<String>,
+    // Set while the entry is in the trash
+    pub deleted_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+// A time entry added by hand, as sent by the frontend
+#[derive(Debug, Deserialize, TS)]
+#[ts(export)]
+pub struct NewTimeEntry {
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub activity: Option<String>,
+    pub area: Option<String>,
+    #[serde(default)]
+    #[ts(optional)]
+    pub tags: Option<Vec<String>>,
+}
+
+// Changes to an existing entry. Fields left out stay as they are, an empty activity or area
+// clears it. Tags replace the ones the entry has.
+#[derive(Debug, Default, Deserialize, TS)]
+#[serde(default)]
+#[ts(export)]
+pub struct TimeEntryPatch {
+    #[ts(optional)]
+    pub activity: Option<String>,
+    #[ts(optional)]
+    pub area: Option<String>,
+    #[ts(optional)]
+    pub tags: Option<Vec<String>>,
+    #[ts(optional)]
+    pub start_time: Option<DateTime<Utc>>,
+    #[ts(optional)]
+    pub end_time: Option<DateTime<Utc>>,
+    #[ts(optional)]
+    pub is_pomodoro: Option<bool>,
+}
+
+// Narrows down history and statistics. Discarded entries never show up.
+#[derive(Debug, Clone)]
+pub struct EntryFilter {
+    pub include_cancelled: bool,
+    // Only entries carrying this tag
+    pub tag: Option<String>,
+}
+
+impl Default for EntryFilter {
+    fn default() -> Self {
+        EntryFilter {
+            include_cancelled: true,
+            tag: None,
+        }
+    }
+}
+
 impl TimerEntry {
-    pub fn new(start_time: DateTime<Utc>, is_pomodoro: bool) -> Self {
+    pub fn new(start_time: DateTime<Utc>, is_pomodoro: bool, clock: &dyn Clock) -> Self {
         TimerEntry {
             id: 0,
+            activity_id: None,
             activity: None,
+            area_id: None,
             area: None,
-            start_time: start_time,
+            project_id: 0,
+            project: String::new(),
+            tags: Vec::new(),
+            start_time,
             end_time: None,
             duration: 0,
-            is_pomodoro: is_pomodoro,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
+            is_pomodoro,
+            status: TimerStatus::Running,
+            is_manual: false,
+            paused_at: None,
+            utc_offset: None,
+            timezone: None,
+            deleted_at: None,
+            created_at: clock.now(),
+            updated_at: clock.now(),
         }
     }
 
-    pub fn set_activity(&mut self, activity: String, conn: &Connection) -> Result<()> {
-        self.activity = Some(activity);
-        self.save(conn);
+    pub fn set_activity(
+        &mut self,
+        activity: String,
+        conn: &Connection,
+        clock: &dyn Clock,
+    ) -> Result<()> {
+        self.assign_activity(Some(activity), conn, clock)?;
+        self.save(conn, clock)
+    }
 
+    // Files the entry under the catalogue activity with that name, adding it when it's new. No
+    // name or an empty one clears the activity. The project follows the activity. Saving is
+    // left to the caller.
+    pub fn assign_activity(
+        &mut self,
+        name: Option<String>,
+        conn: &Connection,
+        clock: &dyn Clock,
+    ) -> Result<()> {
+        let item = CatalogueItem::find_or_create(conn, CatalogueKind::Activity, name, clock)?;
+        self.activity_id = item.as_ref().map(|i| i.id);
+        self.activity = item.map(|i| i.name);
+
+        (self.project_id, self.project) = conn.query_row(
+            "SELECT id, name FROM projects
+            WHERE id = (SELECT project_id FROM activities WHERE id = ?1) OR is_default
+            ORDER BY is_default LIMIT 1",
+            rusqlite::params![self.activity_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
         Ok(())
     }
 
-    pub fn end(&mut self, conn: &Connection) -> Result<()> {
-        self.end_time = Some(Utc::now());
-        self.save(conn);
+    pub fn assign_area(
+        &mut self,
+        name: Option<String>,
+        conn: &Connection,
+        clock: &dyn Clock,
+    ) -> Result<()> {
+        let item = CatalogueItem::find_or_create(conn, CatalogueKind::Area, name, clock)?;
+        self.area_id = item.as_ref().map(|i| i.id);
+        self.area = item.map(|i| i.name);
+        Ok(())
+    }
+
+    pub fn set_tags(
+        &mut self,
+        tags: Vec<String>,
+        conn: &Connection,
+        clock: &dyn Clock,
+    ) -> Result<()> {
+        let tags = tags::normalize(tags);
+        tags::set_for_timer(conn, self.id, &tags, clock)?;
+        self.tags = tags::for_timer(conn, self.id)?;
+        Ok(())
+    }
+
+    // Stops the timer, `status` tells how it ended
+    pub fn end(
+        &mut self,
+        end_time: DateTime<Utc>,
+        status: TimerStatus,
+        conn: &Connection,
+        clock: &dyn Clock,
+    ) -> Result<()> {
+        TimerPause::end_open(conn, self.id, end_time, clock)?;
+        self.paused_at = None;
+        self.status = status;
+        self.end_time = Some(end_time);
+        self.duration = self.active_seconds(conn, end_time)?;
+        self.save(conn, clock)
+    }
+
+    // Moves the entry to a new range and recomputes its duration. The offset is taken again in
+    // the zone the entry was recorded in, the move may cross a DST change.
+    pub fn set_range(
+        &mut self,
+        start_time: DateTime<Utc>,
+        end_time: Option<DateTime<Utc>>,
+        conn: &Connection,
+        clock: &dyn Clock,
+    ) -> Result<()> {
+        self.start_time = start_time;
+        self.end_time = end_time;
+        self.refresh_utc_offset();
+        let until = end_time.or(self.paused_at).unwrap_or_else(|| clock.now());
+        self.duration = self.active_seconds(conn, until)?;
+        Ok(())
+    }
+
+    // Takes the offset again for the current start time, in the zone the entry was recorded in
+    pub fn refresh_utc_offset(&mut self) {
+        if let Some(timezone) = self.timezone.as_deref() {
+            if let Ok(calendar) = Calendar::from_settings(Some(timezone), 0) {
+                self.utc_offset = Some(calendar.utc_offset_at(self.start_time));
+            }
+        }
+    }
 
+    // Hands pauses, pomodoro phases and tags over to `target_id` and removes this entry, which
+    // is now part of the target
+    pub fn merge_into(&self, target_id: i32, conn: &Connection) -> Result<()> {
+        TimerPause::reassign(conn, self.id, target_id)?;
+        tags::copy(conn, self.id, target_id)?;
+        conn.execute(
+            "UPDATE pomodoro_phases SET timer_id = ?1 WHERE timer_id = ?2",
+            rusqlite::params![target_id, self.id],
+        )?;
+        conn.execute(
+            "DELETE FROM timers WHERE id = ?1",
+            rusqlite::params![self.id],
+        )?;
+        Ok(())
+    }
+
+    // Moves the entry to the trash, or back out of it with `None`
+    pub fn set_deleted_at(
+        &mut self,
+        deleted_at: Option<DateTime<Utc>>,
+        conn: &Connection,
+        clock: &dyn Clock,
+    ) -> Result<()> {
+        conn.execute(
+            "UPDATE timers SET deleted_at = ?1, updated_at = ?2 WHERE id = ?3",
+            rusqlite::params![
+                deleted_at.map(|d| d.to_rfc3339()),
+                clock.now().to_rfc3339(),
+                self.id
+            ],
+        )?;
+        self.deleted_at = deleted_at;
         Ok(())
     }
 
-    pub fn find(id: i32, conn: &Connection) -> Result<Option<Self>> {
+    // Removes deleted entries for good, `None` empties the whole trash. Returns how many were
+    // removed.
+    pub fn purge(conn: &Connection, id: Option<i32>) -> Result<usize> {
+        // Pauses and tags go with the entry, pomodoro phases are kept without it
+        conn.execute(
+            "DELETE FROM timers WHERE deleted_at IS NOT NULL AND (?1 IS NULL OR id = ?1)",
+            rusqlite::params![id],
+        )
+    }
+
+    pub fn pause(&mut self, at: DateTime<Utc>, conn: &Connection, clock: &dyn Clock) -> Result<()> {
+        TimerPause::create(conn, self.id, at, clock)?;
+        self.paused_at = Some(at);
+        self.status = TimerStatus::Paused;
+        self.duration = self.active_seconds(conn, at)?;
+        self.save(conn, clock)
+    }
+
+    pub fn resume(
+        &mut self,
+        at: DateTime<Utc>,
+        conn: &Connection,
+        clock: &dyn Clock,
+    ) -> Result<()> {
+        TimerPause::end_open(conn, self.id, at, clock)?;
+        self.paused_at = None;
+        self.status = TimerStatus::Running;
+        self.duration = self.active_seconds(conn, at)?;
+        self.save(conn, clock)
+    }
+
+    // Seconds between start and `until` that were not spent paused
+    fn active_seconds(&self, conn: &Connection, until: DateTime<Utc>) -> Result<i32> {
+        let elapsed = (until - self.start_time).num_seconds();
+        let paused = TimerPause::paused_seconds(conn, self.id, self.start_time, until)?;
+
+        Ok((elapsed - paused).max(0) as i32)
+    }
+
+    pub fn find(id: i32, conn: &Connection, clock: &dyn Clock) -> Result<Option<Self>> {
         let row: Option<Self> = conn
             .query_row(
-                "SELECT * FROM timers WHERE id = ?1",
+                &format!(
+                    "SELECT {} FROM timers WHERE id = ?1 AND deleted_at IS NULL",
+                    COLUMNS
+                ),
                 rusqlite::params![id],
-                Self::from_row,
+                |row| Self::from_row(row, clock),
             )
             .optional()?;
 
-        match row {
-            Some(mut record) => Ok(Some(record)),
-            None => Ok(None),
-        }
+        Ok(row)
     }
 
-    pub fn get_active_entry(conn: &Connection) -> Result<Option<Self>> {
+    pub fn get_active_entry(conn: &Connection, clock: &dyn Clock) -> Result<Option<Self>> {
         let row: Option<Self> = conn
             .query_row(
-                "SELECT * FROM timers WHERE end_time IS NULL ORDER BY start_time DESC LIMIT 1",
+                &format!(
+                    "SELECT {} FROM timers WHERE end_time IS NULL AND deleted_at IS NULL ORDER BY start_time DESC LIMIT 1",
+                    COLUMNS
+                ),
                 rusqlite::params![],
-                Self::from_row,
+                |row| Self::from_row(row, clock),
+            )
+            .optional()?;
+
+        Ok(row)
+    }
+
+    pub fn find_deleted(id: i32, conn: &Connection, clock: &dyn Clock) -> Result<Option<Self>> {
+        let row: Option<Self> = conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM timers WHERE id = ?1 AND deleted_at IS NOT NULL",
+                    COLUMNS
+                ),
+                rusqlite::params![id],
+                |row| Self::from_row(row, clock),
             )
             .optional()?;
 
-        match row {
-            Some(record) => Ok(Some(record)),
-            None => Ok(None),
+        Ok(row)
+    }
+
+    // Deleted entries, most recently deleted first
+    pub fn get_deleted(conn: &Connection, clock: &dyn Clock) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM timers WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC",
+            COLUMNS
+        ))?;
+        let rows = stmt.query_map([], |row| Self::from_row(row, clock))?;
+
+        let mut vec: Vec<TimerEntry> = Vec::new();
+
+        for row in rows {
+            vec.push(row?);
+        }
+
+        Ok(vec)
+    }
+
+    // Every entry without an end time, oldest first. Only one should exist at a time.
+    pub fn get_open_entries(conn: &Connection, clock: &dyn Clock) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM timers WHERE end_time IS NULL AND deleted_at IS NULL ORDER BY start_time ASC",
+            COLUMNS
+        ))?;
+        let rows = stmt.query_map([], |row| Self::from_row(row, clock))?;
+
+        let mut vec: Vec<TimerEntry> = Vec::new();
+
+        for row in rows {
+            vec.push(row?);
+        }
+
+        Ok(vec)
+    }
+
+    // Latest ended entries matching the filter
+    pub fn get_history(
+        conn: &Connection,
+        filter: &EntryFilter,
+        clock: &dyn Clock,
+    ) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM timers
+            WHERE end_time IS NOT NULL AND deleted_at IS NULL
+                AND status != 'discarded' AND (?1 OR status != 'cancelled')
+                AND (?2 IS NULL OR EXISTS (
+                    SELECT 1 FROM timer_tags ft JOIN tags f ON f.id = ft.tag_id
+                    WHERE ft.timer_id = timers.id AND f.name = ?2
+                ))
+            ORDER BY start_time DESC LIMIT 5",
+            COLUMNS
+        ))?;
+        let rows = stmt.query_map(
+            rusqlite::params![filter.include_cancelled, filter.tag],
+            |row| Self::from_row(row, clock),
+        )?;
+
+        let mut vec: Vec<TimerEntry> = Vec::new();
+
+        for row in rows {
+            vec.push(row?);
         }
+
+        Ok(vec)
     }
 
-    pub fn get_history(conn: &Connection) -> Result<Vec<Self>> {
-        let mut stmt = conn.prepare(
-            "SELECT * FROM timers WHERE end_time IS NOT NULL ORDER BY start_time DESC LIMIT 5",
+    // Entries sharing some time with the range, oldest first. A running entry lasts until now,
+    // discarded ones take no time.
+    pub fn get_overlapping(
+        conn: &Connection,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        clock: &dyn Clock,
+    ) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM timers
+            WHERE julianday(start_time) < julianday(?2)
+                AND (end_time IS NULL OR julianday(end_time) > julianday(?1))
+                AND deleted_at IS NULL AND status != 'discarded'
+            ORDER BY start_time ASC",
+            COLUMNS
+        ))?;
+        let rows = stmt.query_map(
+            rusqlite::params![start_time.to_rfc3339(), end_time.to_rfc3339()],
+            |row| Self::from_row(row, clock),
         )?;
-        let rows = stmt.query_map([], Self::from_row)?;
 
         let mut vec: Vec<TimerEntry> = Vec::new();
 
         for row in rows {
-            vec.push(row.unwrap());
+            vec.push(row?);
         }
 
         Ok(vec)
     }
 
-    pub fn get_history_by_date(conn: &Connection) -> Result<HashMap<NaiveDate, Vec<Self>>> {
+    pub fn get_history_by_date(
+        conn: &Connection,
+        calendar: &Calendar,
+        filter: &EntryFilter,
+        clock: &dyn Clock,
+    ) -> Result<HashMap<NaiveDate, Vec<Self>>> {
         let mut map: HashMap<NaiveDate, Vec<Self>> = HashMap::new();
 
-        let timers = TimerEntry::get_history(conn)?;
+        let timers = TimerEntry::get_history(conn, filter, clock)?;
 
         for t in timers {
-            let date_key = t.start_time.date().naive_local();
-            map.entry(date_key).or_insert_with(Vec::new).push(t);
+            let date_key = calendar.entry_day(t.start_time, t.utc_offset);
+            map.entry(date_key).or_default().push(t);
         }
 
         Ok(map)
     }
 
-    // Create a new record in the database with the given start_time
+    // Create a new record in the database with the given start_time, stamped with the zone of
+    // the calendar it was started in
     pub fn create(
         activity: Option<String>,
         area: Option<String>,
         conn: &Connection,
         start_time: DateTime<Utc>,
         is_pomodoro: bool,
+        calendar: &Calendar,
+        clock: &dyn Clock,
     ) -> Result<Self> {
-        let mut new_row = Self::new(start_time, is_pomodoro);
+        let mut new_row = Self::new(start_time, is_pomodoro, clock);
+        new_row.assign_activity(activity, conn, clock)?;
+        new_row.assign_area(area, conn, clock)?;
+        new_row.stamp_zone(calendar);
+        new_row.insert(conn)?;
+        Ok(new_row)
+    }
+
+    // Create a finished record for time that was not tracked when it happened
+    pub fn create_manual(
+        activity: Option<String>,
+        area: Option<String>,
+        conn: &Connection,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        calendar: &Calendar,
+        clock: &dyn Clock,
+    ) -> Result<Self> {
+        let mut new_row = Self::new(start_time, false, clock);
+        new_row.assign_activity(activity, conn, clock)?;
+        new_row.assign_area(area, conn, clock)?;
+        new_row.end_time = Some(end_time);
+        new_row.duration = (end_time - start_time).num_seconds() as i32;
+        new_row.status = TimerStatus::Finished;
+        new_row.is_manual = true;
+        new_row.stamp_zone(calendar);
+        new_row.insert(conn)?;
+        Ok(new_row)
+    }
+
+    fn stamp_zone(&mut self, calendar: &Calendar) {
+        self.utc_offset = Some(calendar.utc_offset_at(self.start_time));
+        self.timezone = Some(calendar.timezone_name().to_string());
+    }
+
+    pub fn insert(&mut self, conn: &Connection) -> Result<()> {
         conn.execute(
-            "INSERT INTO timers (start_time, activity, area, is_pomodoro, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            rusqlite::params![new_row.start_time.to_rfc3339(), activity, area, new_row.is_pomodoro, new_row.created_at.to_rfc3339(), new_row.updated_at.to_rfc3339()],
+            "INSERT INTO timers (start_time, end_time, duration, activity_id, area_id, is_pomodoro, status, is_manual, utc_offset, timezone, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            rusqlite::params![
+                self.start_time.to_rfc3339(),
+                self.end_time.as_ref().map(|t| t.to_rfc3339()),
+                self.duration,
+                self.activity_id,
+                self.area_id,
+                self.is_pomodoro,
+                self.status,
+                self.is_manual,
+                self.utc_offset,
+                self.timezone,
+                self.created_at.to_rfc3339(),
+                self.updated_at.to_rfc3339(),
+            ],
         )?;
-        new_row.id = conn.last_insert_rowid() as i32;
-        Ok(new_row)
+        self.id = conn.last_insert_rowid() as i32;
+        Ok(())
     }
 
     // Save the current record to the database
-    pub fn save(&self, conn: &Connection) -> Result<()> {
+    pub fn save(&self, conn: &Connection, clock: &dyn Clock) -> Result<()> {
         log::debug!("Updating timer {:?}", self);
 
-        let mut duration = self.duration;
-
-        match self.end_time {
-            Some(e) => {
-                let difference = e - self.start_time;
-                duration = difference.num_seconds() as i32;
-            }
-            None => {}
-        }
-
         conn.execute(
-            "UPDATE timers SET activity = ?1, start_time = ?2, end_time = ?3, duration = ?4, is_pomodoro = ?5, updated_at = ?6 WHERE id = ?7",
+            "UPDATE timers SET activity_id = ?1, area_id = ?2, start_time = ?3, end_time = ?4, duration = ?5, is_pomodoro = ?6, status = ?7, utc_offset = ?8, is_manual = ?9, updated_at = ?10 WHERE id = ?11",
             rusqlite::params![
-                self.activity,
+                self.activity_id,
+                self.area_id,
                 self.start_time.to_rfc3339(),
-                // Truly GPT-ed the fuck away out of this one
-                self.end_time.as_ref().map_or(None, |s| Some(s.to_rfc3339())),
-                duration,
+                self.end_time.as_ref().map(|s| s.to_rfc3339()),
+                self.duration,
                 self.is_pomodoro,
-                Utc::now().to_rfc3339(),
+                self.status,
+                self.utc_offset,
+                self.is_manual,
+                clock.now().to_rfc3339(),
                 self.id,
             ],
         )?;
         Ok(())
     }
 
-    fn from_row(row: &Row) -> Result<Self> {
-        let start_time_col: String = row.get(3)?;
-        let start_time =
-            DateTime::<Utc>::from(DateTime::parse_from_rfc3339(&start_time_col).unwrap());
-        let end_time_col: Option<String> = row.get(4)?;
-        let created_at_col: String = row.get(7)?;
-        let updated_at_col: String = row.get(8)?;
+    fn from_row(row: &Row, clock: &dyn Clock) -> Result<Self> {
+        let start_time = get_datetime(row, "start_time")?;
+        let end_time = get_optional_datetime(row, "end_time")?;
+        let paused_at = get_optional_datetime(row, "paused_at")?;
 
-        let mut duration = 0;
-
-        match &end_time_col {
-            Some(e) => {
-                duration = row.get(5)?;
-            }
+        // Running timers report their live duration: time since start minus finished pauses,
+        // frozen at the start of the current pause if there is one.
+        let duration = match end_time {
+            Some(_) => row.get("duration")?,
             None => {
-                let now = Utc::now();
-                let difference = now - start_time;
-                duration = difference.num_seconds();
+                let until = paused_at.unwrap_or_else(|| clock.now());
+                let paused_seconds: i64 = row.get("paused_seconds")?;
+
+                ((until - start_time).num_seconds() - paused_seconds).max(0) as i32
             }
-        }
+        };
 
         Ok(Self {
-            id: row.get(0)?,
-            activity: row.get(1)?,
-            area: row.get(2)?,
-            start_time: DateTime::<Utc>::from(
-                DateTime::parse_from_rfc3339(&start_time_col).unwrap(),
-            ),
-            end_time: match end_time_col {
-                Some(e) => Some(DateTime::<Utc>::from(
-                    DateTime::parse_from_rfc3339(&e).unwrap(),
-                )),
-                None => None,
-            },
-            duration: duration as i32,
-            is_pomodoro: row.get(6)?,
-            created_at: DateTime::<Utc>::from(
-                DateTime::parse_from_rfc3339(&created_at_col).unwrap(),
-            ),
-            updated_at: DateTime::<Utc>::from(
-                DateTime::parse_from_rfc3339(&updated_at_col).unwrap(),
-            ),
+            id: row.get("id")?,
+            activity_id: row.get("activity_id")?,
+            activity: row.get("activity")?,
+            area_id: row.get("area_id")?,
+            area: row.get("area")?,
+            project_id: row.get("project_id")?,
+            project: row.get("project")?,
+            tags: tags::from_column(row.get("tags")?),
+            start_time,
+            end_time,
+            duration,
+            is_pomodoro: row.get("is_pomodoro")?,
+            status: row.get("status")?,
+            is_manual: row.get("is_manual")?,
+            paused_at,
+            utc_offset: row.get("utc_offset")?,
+            timezone: row.get("timezone")?,
+            deleted_at: get_optional_datetime(row, "deleted_at")?,
+            created_at: get_datetime(row, "created_at")?,
+            updated_at: get_datetime(row, "updated_at")?,
         })
     }
 }
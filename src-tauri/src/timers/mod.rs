@@ -1,211 +1,724 @@
 use chrono::prelude::*;
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
+use std::collections::HashMap;
 use tauri::State;
 
+use crate::clock::{Calendar, Clock, OffsetClock};
+use crate::configuration::{ConfigurationState, DayGrouping, OverlapPolicy};
+use crate::errors::TimersError;
+use catalogue::{CatalogueItem, CatalogueKind};
+use entries::{NewTimeEntry, TimeEntryPatch, TimerEntry};
+use pomodoro::PomodoroPhase;
+use projects::{ActivityBilling, Billing, Client, Project};
+use service::TimerService;
+use statistics::{ActivityStatistic, Earnings, EarningsGrouping, TagStatistic, TimerStatistic};
+
+mod catalogue;
 mod entries;
 mod notifications;
+mod pauses;
+mod pomodoro;
+mod projects;
+mod scheduler;
+mod service;
 mod statistics;
+mod tags;
+
+pub use scheduler::PomodoroScheduler;
+
+#[cfg(test)]
+mod tests;
+
+// Startup pass closing open entries left behind by versions that allowed several running
+// timers at once.
+pub fn repair_open_entries(
+    db: &Pool<SqliteConnectionManager>,
+    clock: &dyn Clock,
+    calendar: Calendar,
+) -> Result<usize, TimersError> {
+    let connection = db.get()?;
+
+    TimerService::new(&connection, clock)
+        .with_calendar(calendar)
+        .repair_open_entries()
+}
 
 #[tauri::command]
 pub fn start_pomodoro_timer_command(
     activity: Option<String>,
+    tags: Option<Vec<String>>,
     db: State<Pool<SqliteConnectionManager>>,
-) -> Result<String, String> {
+    clock: State<OffsetClock>,
+    scheduler: State<PomodoroScheduler>,
+    configuration: State<ConfigurationState>,
+) -> Result<TimerEntry, TimersError> {
     log::debug!("Start pomodoro timer command handler started");
 
-    // TODO: Handle errors.
-    let connection = db.get().expect("Failed to get db connection");
-    let now = Utc::now();
+    let configuration = configuration.get();
+    let connection = db.get()?;
+
+    let timer_entry = TimerService::new(&connection, clock.inner())
+        .with_active_timer_policy(configuration.timers.active_timer_policy)
+        .with_pomodoro_settings(configuration.pomodoro.clone())
+        .with_calendar(configuration.calendar())
+        .start_pomodoro(activity, tags.unwrap_or_default())?;
+    scheduler.wake();
 
-    statistics::mark_timer_started(&connection, now).expect("Could not mark timer as started");
-    let _timer_entry = entries::TimerEntry::create(activity, None, &connection, now, true)
-        .expect("Could not start timer entry");
-    notifications::send_timer_start_notification().expect("Could not send notification");
+    if configuration.notifications.timer_started {
+        if let Err(e) = notifications::send_timer_start_notification() {
+            log::warn!("Could not send timer started notification: {}", e);
+        }
+    }
 
-    Ok(String::from("Ok"))
+    Ok(timer_entry)
 }
 
 #[tauri::command]
 pub fn start_timer_command(
     activity: Option<String>,
     area: Option<String>,
+    tags: Option<Vec<String>>,
     db: State<Pool<SqliteConnectionManager>>,
-) -> Result<String, String> {
+    clock: State<OffsetClock>,
+    scheduler: State<PomodoroScheduler>,
+    configuration: State<ConfigurationState>,
+) -> Result<TimerEntry, TimersError> {
     log::debug!("Start timer command handler started");
 
-    // TODO: Handle errors.
-    let connection = db.get().expect("Failed to get db connection");
-    let now = Utc::now();
+    let configuration = configuration.get();
+    let connection = db.get()?;
 
-    let _timer_entry = entries::TimerEntry::create(activity, area, &connection, now, false)
-        .expect("Could not start timer entry");
+    let timer_entry = TimerService::new(&connection, clock.inner())
+        .with_active_timer_policy(configuration.timers.active_timer_policy)
+        .with_calendar(configuration.calendar())
+        .start(activity, area, tags.unwrap_or_default())?;
+    scheduler.wake();
 
-    Ok(String::from("Ok"))
+    Ok(timer_entry)
 }
 
 #[tauri::command]
-pub fn cancel_timer_command(db: State<Pool<SqliteConnectionManager>>) -> Result<String, String> {
+pub fn cancel_timer_command(
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+    scheduler: State<PomodoroScheduler>,
+    configuration: State<ConfigurationState>,
+) -> Result<Option<TimerEntry>, TimersError> {
     log::debug!("Cancel timer command handler started");
 
-    // TODO: Handle errors.
-    let connection = db.get().expect("Failed to get db connection");
-    let now = Utc::now();
-
-    let timer_entry = entries::TimerEntry::get_active_entry(&connection)
-        .expect("could not load timer entry from storage");
-
-    match timer_entry {
-        Some(mut timer_entry) => {
-            timer_entry
-                .end(&connection)
-                .expect("Could not update end time on time entry");
-            statistics::mark_timer_cancelled(&connection, now)
-                .expect("Could not mark timer as cancelled");
-            notifications::send_timer_cancelled_notification()
-                .expect("Could not send notification");
+    let configuration = configuration.get();
+    let connection = db.get()?;
+
+    let timer_entry = TimerService::new(&connection, clock.inner())
+        .with_calendar(configuration.calendar())
+        .cancel()?;
+    scheduler.wake();
+
+    if timer_entry.is_some() && configuration.notifications.timer_cancelled {
+        if let Err(e) = notifications::send_timer_cancelled_notification() {
+            log::warn!("Could not send timer cancelled notification: {}", e);
         }
-        None => {}
     }
 
-    Ok(String::from("Ok"))
+    Ok(timer_entry)
+}
+
+// Throws away a timer started by mistake. Unlike cancelling, it leaves no trace in history or
+// statistics.
+#[tauri::command]
+pub fn discard_timer_command(
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+    scheduler: State<PomodoroScheduler>,
+    configuration: State<ConfigurationState>,
+) -> Result<Option<TimerEntry>, TimersError> {
+    log::debug!("Discard timer command handler started");
+
+    let configuration = configuration.get();
+    let connection = db.get()?;
+
+    let timer_entry = TimerService::new(&connection, clock.inner())
+        .with_calendar(configuration.calendar())
+        .discard()?;
+    scheduler.wake();
+
+    Ok(timer_entry)
 }
 
 #[tauri::command]
-pub fn finish_timer_command(db: State<Pool<SqliteConnectionManager>>) -> Result<String, String> {
+pub fn finish_timer_command(
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+    scheduler: State<PomodoroScheduler>,
+    configuration: State<ConfigurationState>,
+) -> Result<Option<TimerEntry>, TimersError> {
     log::debug!("Finish timer command handler started");
 
-    // TODO: Handle errors.
-    let connection = db.get().expect("Failed to get db connection");
-    let now = Utc::now();
-
-    let timer_entry = entries::TimerEntry::get_active_entry(&connection)
-        .expect("could not load timer entry from storage");
-
-    match timer_entry {
-        Some(mut timer_entry) => {
-            timer_entry
-                .end(&connection)
-                .expect("Could not update end time on time entry");
-
-            if timer_entry.is_pomodoro {
-                statistics::mark_timer_finished(&connection, now)
-                    .expect("Could not mark timer as finished");
-                notifications::send_timer_finished_notification()
-                    .expect("Could not send notification");
-            }
+    let configuration = configuration.get();
+    let connection = db.get()?;
+
+    let timer_entry = TimerService::new(&connection, clock.inner())
+        .with_calendar(configuration.calendar())
+        .finish()?;
+    scheduler.wake();
+
+    if timer_entry.as_ref().is_some_and(|t| t.is_pomodoro)
+        && configuration.notifications.timer_finished
+    {
+        if let Err(e) = notifications::send_timer_finished_notification() {
+            log::warn!("Could not send timer finished notification: {}", e);
         }
-        None => {}
     }
 
-    Ok(String::from("Ok"))
+    Ok(timer_entry)
+}
+
+#[tauri::command]
+pub fn pause_timer_command(
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+    scheduler: State<PomodoroScheduler>,
+) -> Result<TimerEntry, TimersError> {
+    log::debug!("Pause timer command handler started");
+
+    let connection = db.get()?;
+
+    let timer_entry = TimerService::new(&connection, clock.inner()).pause()?;
+    scheduler.wake();
+
+    Ok(timer_entry)
+}
+
+#[tauri::command]
+pub fn resume_timer_command(
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+    scheduler: State<PomodoroScheduler>,
+) -> Result<TimerEntry, TimersError> {
+    log::debug!("Resume timer command handler started");
+
+    let connection = db.get()?;
+
+    let timer_entry = TimerService::new(&connection, clock.inner()).resume()?;
+    scheduler.wake();
+
+    Ok(timer_entry)
 }
 
 #[tauri::command]
 pub fn get_active_timer_command(
     db: State<Pool<SqliteConnectionManager>>,
-) -> Result<String, String> {
+    clock: State<OffsetClock>,
+) -> Result<Option<TimerEntry>, TimersError> {
     log::debug!("Get active timer command handler started");
 
-    let connection = db.get().expect("Failed to get db connection");
+    let connection = db.get()?;
 
-    match entries::TimerEntry::get_active_entry(&connection).expect("Could not fetch entry from db")
-    {
-        Some(t) => Ok(serde_json::to_string(&t).expect("Could not convert time entry to json")),
-        None => Err(String::from("No timer")),
-    }
+    TimerService::new(&connection, clock.inner()).active()
+}
+
+#[tauri::command]
+pub fn get_pomodoro_phase_command(
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+) -> Result<Option<PomodoroPhase>, TimersError> {
+    log::debug!("Get pomodoro phase command handler started");
+
+    let connection = db.get()?;
+
+    TimerService::new(&connection, clock.inner()).pomodoro_phase()
 }
 
 #[tauri::command]
 pub fn update_time_entry_activity_command(
     db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
     activity: String,
     time_entry_id: i32,
-) -> Result<String, String> {
+) -> Result<TimerEntry, TimersError> {
     log::debug!("Update timer entry activity command handler started");
 
-    let connection = db.get().expect("Failed to get db connection");
+    let connection = db.get()?;
 
-    let time_entry =
-        entries::TimerEntry::find(time_entry_id, &connection).expect("Failed to load data from db");
+    TimerService::new(&connection, clock.inner()).update_activity(time_entry_id, activity)
+}
 
-    match time_entry {
-        Some(mut t) => {
-            t.set_activity(activity, &connection);
-            Ok(String::from("Updated"))
-        }
-        None => Err(String::from("Time entry not found")),
-    }
+#[tauri::command]
+pub fn update_time_entry_command(
+    time_entry_id: i32,
+    patch: TimeEntryPatch,
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+    scheduler: State<PomodoroScheduler>,
+    configuration: State<ConfigurationState>,
+) -> Result<TimerEntry, TimersError> {
+    log::debug!("Update time entry command handler started");
+
+    let configuration = configuration.get();
+    let connection = db.get()?;
+
+    let timer_entry = TimerService::new(&connection, clock.inner())
+        .with_calendar(configuration.calendar())
+        .update_entry(time_entry_id, patch)?;
+    scheduler.wake();
+
+    Ok(timer_entry)
+}
+
+// Cuts an entry in two at `at`, the second part gets `new_activity` when given
+#[tauri::command]
+pub fn split_time_entry_command(
+    time_entry_id: i32,
+    at: DateTime<Utc>,
+    new_activity: Option<String>,
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+    scheduler: State<PomodoroScheduler>,
+    configuration: State<ConfigurationState>,
+) -> Result<Vec<TimerEntry>, TimersError> {
+    log::debug!("Split time entry command handler started");
+
+    let configuration = configuration.get();
+    let connection = db.get()?;
+
+    let timer_entries = TimerService::new(&connection, clock.inner())
+        .with_calendar(configuration.calendar())
+        .split_entry(time_entry_id, at, new_activity)?;
+    scheduler.wake();
+
+    Ok(timer_entries)
+}
+
+#[tauri::command]
+pub fn merge_time_entries_command(
+    time_entry_ids: Vec<i32>,
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+    scheduler: State<PomodoroScheduler>,
+    configuration: State<ConfigurationState>,
+) -> Result<TimerEntry, TimersError> {
+    log::debug!("Merge time entries command handler started");
+
+    let configuration = configuration.get();
+    let connection = db.get()?;
+
+    let timer_entry = TimerService::new(&connection, clock.inner())
+        .with_calendar(configuration.calendar())
+        .merge_entries(time_entry_ids)?;
+    scheduler.wake();
+
+    Ok(timer_entry)
+}
+
+#[tauri::command]
+pub fn delete_time_entry_command(
+    time_entry_id: i32,
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+    scheduler: State<PomodoroScheduler>,
+) -> Result<TimerEntry, TimersError> {
+    log::debug!("Delete time entry command handler started");
+
+    let connection = db.get()?;
+
+    let timer_entry = TimerService::new(&connection, clock.inner()).delete_entry(time_entry_id)?;
+    scheduler.wake();
+
+    Ok(timer_entry)
+}
+
+#[tauri::command]
+pub fn restore_time_entry_command(
+    time_entry_id: i32,
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+    scheduler: State<PomodoroScheduler>,
+) -> Result<TimerEntry, TimersError> {
+    log::debug!("Restore time entry command handler started");
+
+    let connection = db.get()?;
+
+    let timer_entry = TimerService::new(&connection, clock.inner()).restore_entry(time_entry_id)?;
+    scheduler.wake();
+
+    Ok(timer_entry)
+}
+
+#[tauri::command]
+pub fn load_trash_command(
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+) -> Result<Vec<TimerEntry>, TimersError> {
+    log::debug!("Load trash command handler started");
+
+    let connection = db.get()?;
+
+    TimerService::new(&connection, clock.inner()).trash()
+}
+
+// Without an id the whole trash is emptied. Returns how many entries were removed.
+#[tauri::command]
+pub fn purge_time_entries_command(
+    time_entry_id: Option<i32>,
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+) -> Result<usize, TimersError> {
+    log::debug!("Purge time entries command handler started");
+
+    let connection = db.get()?;
+
+    TimerService::new(&connection, clock.inner()).purge(time_entry_id)
+}
+
+// Adds time that was not tracked. `policy` overrides the `timers.overlap_policy` setting.
+#[tauri::command]
+pub fn create_time_entry_command(
+    entry: NewTimeEntry,
+    policy: Option<OverlapPolicy>,
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+    configuration: State<ConfigurationState>,
+) -> Result<Vec<TimerEntry>, TimersError> {
+    log::debug!("Create time entry command handler started");
+
+    let configuration = configuration.get();
+    let connection = db.get()?;
+
+    TimerService::new(&connection, clock.inner())
+        .with_overlap_policy(policy.unwrap_or(configuration.timers.overlap_policy))
+        .with_calendar(configuration.calendar())
+        .create_entry(
+            entry.start_time,
+            entry.end_time,
+            entry.activity,
+            entry.area,
+            entry.tags.unwrap_or_default(),
+        )
 }
 
 #[tauri::command]
 pub fn load_timer_entries_history_command(
+    include_cancelled: Option<bool>,
+    tag: Option<String>,
     db: State<Pool<SqliteConnectionManager>>,
-) -> Result<String, String> {
+    clock: State<OffsetClock>,
+) -> Result<Vec<TimerEntry>, TimersError> {
     log::debug!("Load timer entries history command handler started");
 
-    let connection = db.get().expect("Failed to get db connection");
-
-    let history =
-        entries::TimerEntry::get_history(&connection).expect("Failed to get data from db");
+    let connection = db.get()?;
 
-    Ok(serde_json::to_string(&history).expect("Failed to encode to json"))
+    TimerService::new(&connection, clock.inner())
+        .with_cancelled(include_cancelled.unwrap_or(true))
+        .with_tag(tag)
+        .history()
 }
 
 #[tauri::command]
 pub fn load_daily_history_command(
+    group_by: Option<DayGrouping>,
+    include_cancelled: Option<bool>,
+    tag: Option<String>,
     db: State<Pool<SqliteConnectionManager>>,
-) -> Result<String, String> {
+    clock: State<OffsetClock>,
+    configuration: State<ConfigurationState>,
+) -> Result<HashMap<NaiveDate, Vec<TimerEntry>>, TimersError> {
     log::debug!("Load daily history command handler started");
 
-    let connection = db.get().expect("Failed to get db connection");
-
-    let daily_history = entries::TimerEntry::get_history_by_date(&connection).unwrap();
-
-    Ok(serde_json::to_string(&daily_history).unwrap())
+    let configuration = configuration.get();
+    let connection = db.get()?;
+    // Reports can look at days either way, whatever the settings say
+    let calendar = configuration.calendar();
+    let calendar = group_by.map_or(calendar, |g| calendar.with_grouping(g));
+
+    TimerService::new(&connection, clock.inner())
+        .with_calendar(calendar)
+        .with_cancelled(include_cancelled.unwrap_or(true))
+        .with_tag(tag)
+        .history_by_date()
 }
 
 #[tauri::command]
 pub fn load_daily_statistics_command(
     db: State<Pool<SqliteConnectionManager>>,
-) -> Result<String, String> {
+    clock: State<OffsetClock>,
+    configuration: State<ConfigurationState>,
+) -> Result<TimerStatistic, TimersError> {
     log::debug!("Loading daily statistics command handler started");
 
-    let connection = db.get().expect("Failed to get db connection");
-    let now = Utc::now();
-
-    let statistics =
-        statistics::load_statistic_for_date(&connection, now).expect("Could not load statistics");
+    let configuration = configuration.get();
+    let connection = db.get()?;
 
-    Ok(serde_json::to_string(&statistics).expect("Could not serialize statistics"))
+    TimerService::new(&connection, clock.inner())
+        .with_calendar(configuration.calendar())
+        .daily_statistics()
 }
 
 #[tauri::command]
 pub fn load_statistics_history_command(
     db: State<Pool<SqliteConnectionManager>>,
-) -> Result<String, String> {
+    clock: State<OffsetClock>,
+    configuration: State<ConfigurationState>,
+) -> Result<Vec<TimerStatistic>, TimersError> {
     log::debug!("Loading statistics history command handler started");
 
-    let connection = db.get().expect("Failed to get db connection");
+    let configuration = configuration.get();
+    let connection = db.get()?;
 
-    let statistics = statistics::TimerStatistic::load_statistics_history(&connection, 10)
-        .expect("Could not load statistics");
-
-    Ok(serde_json::to_string(&statistics).expect("Could not serialize statistics"))
+    TimerService::new(&connection, clock.inner())
+        .with_calendar(configuration.calendar())
+        .statistics_history(10)
 }
 
 #[tauri::command]
 pub fn load_activity_statistics_for_date_command(
+    group_by: Option<DayGrouping>,
+    include_cancelled: Option<bool>,
+    tag: Option<String>,
     db: State<Pool<SqliteConnectionManager>>,
-) -> Result<String, String> {
+    clock: State<OffsetClock>,
+    configuration: State<ConfigurationState>,
+) -> Result<Vec<ActivityStatistic>, TimersError> {
     log::debug!("Loading activity statistics for date command handler started");
 
-    let connection = db.get().expect("Failed to get db connection");
-    let now = Utc::now();
+    let configuration = configuration.get();
+    let connection = db.get()?;
+    let calendar = configuration.calendar();
+    let calendar = group_by.map_or(calendar, |g| calendar.with_grouping(g));
+
+    TimerService::new(&connection, clock.inner())
+        .with_calendar(calendar)
+        .with_cancelled(include_cancelled.unwrap_or(true))
+        .with_tag(tag)
+        .activity_statistics()
+}
+
+#[tauri::command]
+pub fn load_tag_statistics_for_date_command(
+    group_by: Option<DayGrouping>,
+    include_cancelled: Option<bool>,
+    tag: Option<String>,
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+    configuration: State<ConfigurationState>,
+) -> Result<Vec<TagStatistic>, TimersError> {
+    log::debug!("Loading tag statistics for date command handler started");
+
+    let configuration = configuration.get();
+    let connection = db.get()?;
+    let calendar = configuration.calendar();
+    let calendar = group_by.map_or(calendar, |g| calendar.with_grouping(g));
+
+    TimerService::new(&connection, clock.inner())
+        .with_calendar(calendar)
+        .with_cancelled(include_cancelled.unwrap_or(true))
+        .with_tag(tag)
+        .tag_statistics()
+}
+
+#[tauri::command]
+pub fn load_tags_command(
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+) -> Result<Vec<String>, TimersError> {
+    log::debug!("Load tags command handler started");
+
+    let connection = db.get()?;
+
+    TimerService::new(&connection, clock.inner()).tags()
+}
+
+// Activities or areas by name, archived ones only when asked for
+#[tauri::command]
+pub fn load_catalogue_command(
+    kind: CatalogueKind,
+    include_archived: Option<bool>,
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+) -> Result<Vec<CatalogueItem>, TimersError> {
+    log::debug!("Load catalogue command handler started");
+
+    let connection = db.get()?;
+
+    TimerService::new(&connection, clock.inner()).catalogue(kind, include_archived.unwrap_or(false))
+}
+
+#[tauri::command]
+pub fn rename_catalogue_item_command(
+    kind: CatalogueKind,
+    id: i32,
+    name: String,
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+) -> Result<CatalogueItem, TimersError> {
+    log::debug!("Rename catalogue item command handler started");
+
+    let connection = db.get()?;
+
+    TimerService::new(&connection, clock.inner()).rename_catalogue_item(kind, id, name)
+}
+
+#[tauri::command]
+pub fn merge_catalogue_items_command(
+    kind: CatalogueKind,
+    source_id: i32,
+    target_id: i32,
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+) -> Result<CatalogueItem, TimersError> {
+    log::debug!("Merge catalogue items command handler started");
+
+    let connection = db.get()?;
+
+    TimerService::new(&connection, clock.inner()).merge_catalogue_items(kind, source_id, target_id)
+}
+
+#[tauri::command]
+pub fn archive_catalogue_item_command(
+    kind: CatalogueKind,
+    id: i32,
+    archived: bool,
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+) -> Result<CatalogueItem, TimersError> {
+    log::debug!("Archive catalogue item command handler started");
+
+    let connection = db.get()?;
+
+    TimerService::new(&connection, clock.inner()).set_catalogue_item_archived(kind, id, archived)
+}
 
-    let statistics =
-        statistics::ActivityStatistic::load_activity_statistics_for_date(&connection, now)
-            .expect("Could not load statistics");
+#[tauri::command]
+pub fn load_clients_command(
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+) -> Result<Vec<Client>, TimersError> {
+    log::debug!("Load clients command handler started");
 
-    Ok(serde_json::to_string(&statistics).expect("Could not serialize statistics"))
+    let connection = db.get()?;
+
+    TimerService::new(&connection, clock.inner()).clients()
+}
+
+#[tauri::command]
+pub fn create_client_command(
+    name: String,
+    billing: Billing,
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+) -> Result<Client, TimersError> {
+    log::debug!("Create client command handler started");
+
+    let connection = db.get()?;
+
+    TimerService::new(&connection, clock.inner()).create_client(name, billing)
+}
+
+#[tauri::command]
+pub fn update_client_command(
+    client_id: i32,
+    name: String,
+    billing: Billing,
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+) -> Result<Client, TimersError> {
+    log::debug!("Update client command handler started");
+
+    let connection = db.get()?;
+
+    TimerService::new(&connection, clock.inner()).update_client(client_id, name, billing)
+}
+
+#[tauri::command]
+pub fn load_projects_command(
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+) -> Result<Vec<Project>, TimersError> {
+    log::debug!("Load projects command handler started");
+
+    let connection = db.get()?;
+
+    TimerService::new(&connection, clock.inner()).projects()
+}
+
+#[tauri::command]
+pub fn create_project_command(
+    client_id: Option<i32>,
+    name: String,
+    billing: Billing,
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+) -> Result<Project, TimersError> {
+    log::debug!("Create project command handler started");
+
+    let connection = db.get()?;
+
+    TimerService::new(&connection, clock.inner()).create_project(client_id, name, billing)
+}
+
+#[tauri::command]
+pub fn update_project_command(
+    project_id: i32,
+    client_id: Option<i32>,
+    name: String,
+    billing: Billing,
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+) -> Result<Project, TimersError> {
+    log::debug!("Update project command handler started");
+
+    let connection = db.get()?;
+
+    TimerService::new(&connection, clock.inner())
+        .update_project(project_id, client_id, name, billing)
+}
+
+#[tauri::command]
+pub fn load_activity_billing_command(
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+) -> Result<Vec<ActivityBilling>, TimersError> {
+    log::debug!("Load activity billing command handler started");
+
+    let connection = db.get()?;
+
+    TimerService::new(&connection, clock.inner()).activity_billing()
+}
+
+// Files an activity under a project, `None` moves it back to the default project
+#[tauri::command]
+pub fn assign_activity_command(
+    activity_id: i32,
+    project_id: Option<i32>,
+    billing: Billing,
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+) -> Result<ActivityBilling, TimersError> {
+    log::debug!("Assign activity command handler started");
+
+    let connection = db.get()?;
+
+    TimerService::new(&connection, clock.inner()).assign_activity(activity_id, project_id, billing)
+}
+
+// Earnings of the days from `from` to `to`, both included
+#[tauri::command]
+pub fn load_earnings_command(
+    from: NaiveDate,
+    to: NaiveDate,
+    grouping: EarningsGrouping,
+    include_cancelled: Option<bool>,
+    db: State<Pool<SqliteConnectionManager>>,
+    clock: State<OffsetClock>,
+    configuration: State<ConfigurationState>,
+) -> Result<Vec<Earnings>, TimersError> {
+    log::debug!("Load earnings command handler started");
+
+    let configuration = configuration.get();
+    let connection = db.get()?;
+
+    TimerService::new(&connection, clock.inner())
+        .with_calendar(configuration.calendar())
+        .with_cancelled(include_cancelled.unwrap_or(true))
+        .earnings(from, to, grouping)
 }
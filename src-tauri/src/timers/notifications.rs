@@ -1,34 +1,53 @@
 use notify_rust::Notification;
 
-pub fn send_timer_start_notification() -> Result<(), ()> {
-    println!("Sending notification");
+use crate::errors::TimersError;
+
+pub fn send_timer_start_notification() -> Result<(), TimersError> {
+    log::debug!("Sending timer started notification");
     Notification::new()
         .summary("H.Q.! Timer Started")
         .body("Your timer has been started.")
-        .show()
-        .unwrap();
+        .show()?;
 
     Ok(())
 }
 
-pub fn send_timer_finished_notification() -> Result<(), ()> {
-    println!("Sending notification");
+pub fn send_timer_finished_notification() -> Result<(), TimersError> {
+    log::debug!("Sending timer finished notification");
     Notification::new()
         .summary("H.Q.! Timer Finished")
         .body("Your timer has finished.")
-        .show()
-        .unwrap();
+        .show()?;
 
     Ok(())
 }
 
-pub fn send_timer_cancelled_notification() -> Result<(), ()> {
-    println!("Sending notification");
+pub fn send_timer_cancelled_notification() -> Result<(), TimersError> {
+    log::debug!("Sending timer cancelled notification");
     Notification::new()
         .summary("H.Q.! Timer Cancelled")
         .body("Your timer has been cancelled.")
-        .show()
-        .unwrap();
+        .show()?;
+
+    Ok(())
+}
+
+pub fn send_break_started_notification(minutes: u32) -> Result<(), TimersError> {
+    log::debug!("Sending break started notification");
+    Notification::new()
+        .summary("H.Q.! Break Time")
+        .body(&format!("Take a {} minute break.", minutes))
+        .show()?;
+
+    Ok(())
+}
+
+pub fn send_break_finished_notification() -> Result<(), TimersError> {
+    log::debug!("Sending break finished notification");
+    Notification::new()
+        .summary("H.Q.! Break Over")
+        .body("Your break has ended.")
+        .show()?;
 
     Ok(())
 }
@@ -0,0 +1,179 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, Result, Row};
+
+use crate::clock::Clock;
+use crate::storage::{get_datetime, get_optional_datetime};
+
+// A stretch of time during which a running timer did not count. A timer has at most one open
+// pause (no `ended_at`), and only while it is running.
+#[derive(Debug)]
+pub struct TimerPause {
+    pub id: i32,
+    pub timer_id: i32,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+impl TimerPause {
+    pub fn create(
+        conn: &Connection,
+        timer_id: i32,
+        started_at: DateTime<Utc>,
+        clock: &dyn Clock,
+    ) -> Result<Self> {
+        let now = clock.now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO timer_pauses (timer_id, started_at, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![timer_id, started_at.to_rfc3339(), now, now],
+        )?;
+
+        Ok(TimerPause {
+            id: conn.last_insert_rowid() as i32,
+            timer_id,
+            started_at,
+            ended_at: None,
+        })
+    }
+
+    // Records time between two merged entries, when nothing was tracked
+    pub fn create_ended(
+        conn: &Connection,
+        timer_id: i32,
+        started_at: DateTime<Utc>,
+        ended_at: DateTime<Utc>,
+        clock: &dyn Clock,
+    ) -> Result<Self> {
+        let now = clock.now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO timer_pauses (timer_id, started_at, ended_at, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![timer_id, started_at.to_rfc3339(), ended_at.to_rfc3339(), now, now],
+        )?;
+
+        Ok(TimerPause {
+            id: conn.last_insert_rowid() as i32,
+            timer_id,
+            started_at,
+            ended_at: Some(ended_at),
+        })
+    }
+
+    // Hands every pause of one timer over to another
+    pub fn reassign(conn: &Connection, from_timer_id: i32, to_timer_id: i32) -> Result<()> {
+        conn.execute(
+            "UPDATE timer_pauses SET timer_id = ?1 WHERE timer_id = ?2",
+            rusqlite::params![to_timer_id, from_timer_id],
+        )?;
+        Ok(())
+    }
+
+    // Hands the pauses after `at` over to the timer continuing from there. A pause going on at
+    // that moment is cut in two.
+    pub fn split_at(
+        conn: &Connection,
+        from_timer_id: i32,
+        to_timer_id: i32,
+        at: DateTime<Utc>,
+        clock: &dyn Clock,
+    ) -> Result<()> {
+        let mut stmt = conn.prepare(
+            "SELECT * FROM timer_pauses WHERE timer_id = ?1 AND julianday(started_at) < julianday(?2)
+                AND (ended_at IS NULL OR julianday(ended_at) > julianday(?2))",
+        )?;
+        let spanning = stmt
+            .query_row(
+                rusqlite::params![from_timer_id, at.to_rfc3339()],
+                Self::from_row,
+            )
+            .optional()?;
+
+        conn.execute(
+            "UPDATE timer_pauses SET timer_id = ?1 WHERE timer_id = ?2 AND julianday(started_at) >= julianday(?3)",
+            rusqlite::params![to_timer_id, from_timer_id, at.to_rfc3339()],
+        )?;
+
+        if let Some(pause) = spanning {
+            conn.execute(
+                "UPDATE timer_pauses SET ended_at = ?1, updated_at = ?2 WHERE id = ?3",
+                rusqlite::params![at.to_rfc3339(), clock.now().to_rfc3339(), pause.id],
+            )?;
+
+            match pause.ended_at {
+                Some(ended_at) => {
+                    Self::create_ended(conn, to_timer_id, at, ended_at, clock)?;
+                }
+                None => {
+                    Self::create(conn, to_timer_id, at, clock)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn find_open(conn: &Connection, timer_id: i32) -> Result<Option<Self>> {
+        conn.query_row(
+            "SELECT * FROM timer_pauses WHERE timer_id = ?1 AND ended_at IS NULL",
+            rusqlite::params![timer_id],
+            Self::from_row,
+        )
+        .optional()
+    }
+
+    // Closes the open pause of a timer, if any. A pause can't end before it started.
+    pub fn end_open(
+        conn: &Connection,
+        timer_id: i32,
+        ended_at: DateTime<Utc>,
+        clock: &dyn Clock,
+    ) -> Result<Option<Self>> {
+        match Self::find_open(conn, timer_id)? {
+            Some(mut pause) => {
+                let ended_at = ended_at.max(pause.started_at);
+                log::debug!("Ending pause {} of timer {}", pause.id, pause.timer_id);
+                conn.execute(
+                    "UPDATE timer_pauses SET ended_at = ?1, updated_at = ?2 WHERE id = ?3",
+                    rusqlite::params![ended_at.to_rfc3339(), clock.now().to_rfc3339(), pause.id],
+                )?;
+                pause.ended_at = Some(ended_at);
+
+                Ok(Some(pause))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // Seconds a timer spent paused between `from` and `until`, counting an open pause as still
+    // going. Only the part of a pause inside the range counts, the range may have been moved
+    // after the pause was taken.
+    pub fn paused_seconds(
+        conn: &Connection,
+        timer_id: i32,
+        from: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<i64> {
+        let mut stmt = conn.prepare("SELECT * FROM timer_pauses WHERE timer_id = ?1")?;
+        let rows = stmt.query_map(rusqlite::params![timer_id], Self::from_row)?;
+
+        let mut total = 0;
+        for row in rows {
+            let pause = row?;
+            let started_at = pause.started_at.max(from);
+            let ended_at = pause.ended_at.unwrap_or(until).min(until);
+
+            if ended_at > started_at {
+                total += (ended_at - started_at).num_seconds();
+            }
+        }
+
+        Ok(total)
+    }
+
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            timer_id: row.get("timer_id")?,
+            started_at: get_datetime(row, "started_at")?,
+            ended_at: get_optional_datetime(row, "ended_at")?,
+        })
+    }
+}
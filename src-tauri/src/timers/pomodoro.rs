@@ -0,0 +1,196 @@
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use rusqlite::{Connection, OptionalExtension, Result, Row};
+use serde::Serialize;
+use ts_rs::TS;
+
+use super::entries::TimerEntry;
+use crate::clock::Clock;
+use crate::configuration::PomodoroSettings;
+use crate::storage::{get_datetime, get_optional_datetime};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum PomodoroPhaseKind {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl PomodoroPhaseKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PomodoroPhaseKind::Work => "work",
+            PomodoroPhaseKind::ShortBreak => "short_break",
+            PomodoroPhaseKind::LongBreak => "long_break",
+        }
+    }
+
+    pub fn length(&self, settings: &PomodoroSettings) -> Duration {
+        let minutes = match self {
+            PomodoroPhaseKind::Work => settings.work_minutes,
+            PomodoroPhaseKind::ShortBreak => settings.short_break_minutes,
+            PomodoroPhaseKind::LongBreak => settings.long_break_minutes,
+        };
+
+        Duration::minutes(minutes.into())
+    }
+}
+
+impl ToSql for PomodoroPhaseKind {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for PomodoroPhaseKind {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "work" => Ok(PomodoroPhaseKind::Work),
+            "short_break" => Ok(PomodoroPhaseKind::ShortBreak),
+            "long_break" => Ok(PomodoroPhaseKind::LongBreak),
+            other => Err(FromSqlError::Other(
+                format!("Unknown pomodoro phase {}", other).into(),
+            )),
+        }
+    }
+}
+
+// One step of a pomodoro cycle. Work phases point at the time entry they track, breaks have
+// no entry. At most one phase is open (no `ended_at`) at a time.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct PomodoroPhase {
+    pub id: i32,
+    pub kind: PomodoroPhaseKind,
+    // Position of the work session in the current set, starting at 1
+    pub cycle: i32,
+    pub timer_id: Option<i32>,
+    pub started_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+// What happened when a phase ran out, sent to the frontend as the payload of
+// `pomodoro-phase-changed`.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct PomodoroTransition {
+    pub finished: PomodoroPhase,
+    // Not set when the cycle stopped, e.g. a break that ran out while the app was closed
+    pub started: Option<PomodoroPhase>,
+    // The entry ended by a work phase, or started by the next one
+    pub timer_entry: Option<TimerEntry>,
+}
+
+impl PomodoroPhase {
+    pub fn create(
+        conn: &Connection,
+        kind: PomodoroPhaseKind,
+        cycle: i32,
+        timer_id: Option<i32>,
+        started_at: DateTime<Utc>,
+        settings: &PomodoroSettings,
+        clock: &dyn Clock,
+    ) -> Result<Self> {
+        let ends_at = started_at + kind.length(settings);
+        let now = clock.now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO pomodoro_phases (kind, cycle, timer_id, started_at, ends_at, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![kind, cycle, timer_id, started_at.to_rfc3339(), ends_at.to_rfc3339(), now, now],
+        )?;
+
+        Ok(PomodoroPhase {
+            id: conn.last_insert_rowid() as i32,
+            kind,
+            cycle,
+            timer_id,
+            started_at,
+            ends_at,
+            ended_at: None,
+        })
+    }
+
+    pub fn find_current(conn: &Connection) -> Result<Option<Self>> {
+        conn.query_row(
+            "SELECT * FROM pomodoro_phases WHERE ended_at IS NULL ORDER BY id DESC LIMIT 1",
+            [],
+            Self::from_row,
+        )
+        .optional()
+    }
+
+    // The most recent work session, open or not, to carry its activity into the next one
+    pub fn find_last_work(conn: &Connection) -> Result<Option<Self>> {
+        conn.query_row(
+            "SELECT * FROM pomodoro_phases WHERE kind = ?1 ORDER BY id DESC LIMIT 1",
+            rusqlite::params![PomodoroPhaseKind::Work],
+            Self::from_row,
+        )
+        .optional()
+    }
+
+    pub fn end(
+        &mut self,
+        ended_at: DateTime<Utc>,
+        conn: &Connection,
+        clock: &dyn Clock,
+    ) -> Result<()> {
+        log::debug!("Ending pomodoro phase {} ({})", self.id, self.kind.as_str());
+        self.ended_at = Some(ended_at);
+        self.save(conn, clock)
+    }
+
+    // Pushes the end back, used when the work session was paused
+    pub fn extend(&mut self, by: Duration, conn: &Connection, clock: &dyn Clock) -> Result<()> {
+        self.ends_at += by;
+        self.save(conn, clock)
+    }
+
+    // Moves the whole phase, used when the start of its work session was corrected
+    pub fn shift(&mut self, by: Duration, conn: &Connection, clock: &dyn Clock) -> Result<()> {
+        self.started_at += by;
+        self.ends_at += by;
+        self.save(conn, clock)
+    }
+
+    // The phase that follows this one: breaks after work, a long one to close the set,
+    // and work again after a break.
+    pub fn next(&self, settings: &PomodoroSettings) -> (PomodoroPhaseKind, i32) {
+        match self.kind {
+            PomodoroPhaseKind::Work if self.cycle >= settings.cycles_before_long_break as i32 => {
+                (PomodoroPhaseKind::LongBreak, self.cycle)
+            }
+            PomodoroPhaseKind::Work => (PomodoroPhaseKind::ShortBreak, self.cycle),
+            PomodoroPhaseKind::ShortBreak => (PomodoroPhaseKind::Work, self.cycle + 1),
+            PomodoroPhaseKind::LongBreak => (PomodoroPhaseKind::Work, 1),
+        }
+    }
+
+    fn save(&self, conn: &Connection, clock: &dyn Clock) -> Result<()> {
+        conn.execute(
+            "UPDATE pomodoro_phases SET started_at = ?1, ends_at = ?2, ended_at = ?3, updated_at = ?4 WHERE id = ?5",
+            rusqlite::params![
+                self.started_at.to_rfc3339(),
+                self.ends_at.to_rfc3339(),
+                self.ended_at.as_ref().map(|e| e.to_rfc3339()),
+                clock.now().to_rfc3339(),
+                self.id,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            kind: row.get("kind")?,
+            cycle: row.get("cycle")?,
+            timer_id: row.get("timer_id")?,
+            started_at: get_datetime(row, "started_at")?,
+            ends_at: get_datetime(row, "ends_at")?,
+            ended_at: get_optional_datetime(row, "ended_at")?,
+        })
+    }
+}
@@ -0,0 +1,297 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, Result, Row};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::clock::Clock;
+use crate::storage::get_datetime;
+
+// Billing terms of a client, project or activity. Terms left unset are taken from the level
+// above, activity from project and project from client.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(default)]
+#[ts(export)]
+pub struct Billing {
+    pub billable: Option<bool>,
+    // ISO 4217 code
+    pub currency: Option<String>,
+    // In hundredths of the currency
+    pub hourly_rate: Option<i32>,
+}
+
+impl Billing {
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            billable: row.get("billable")?,
+            currency: row.get("currency")?,
+            hourly_rate: row.get("hourly_rate")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct Client {
+    pub id: i32,
+    pub name: String,
+    pub billing: Billing,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Client {
+    pub fn create(
+        conn: &Connection,
+        name: String,
+        billing: Billing,
+        clock: &dyn Clock,
+    ) -> Result<Self> {
+        let now = clock.now();
+        conn.execute(
+            "INSERT INTO clients (name, billable, currency, hourly_rate, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
+            rusqlite::params![
+                name,
+                billing.billable,
+                billing.currency,
+                billing.hourly_rate,
+                now.to_rfc3339()
+            ],
+        )?;
+
+        Ok(Client {
+            id: conn.last_insert_rowid() as i32,
+            name,
+            billing,
+            created_at: now,
+            updated_at: now,
+        })
+    }
+
+    pub fn find(conn: &Connection, id: i32) -> Result<Option<Self>> {
+        conn.query_row(
+            "SELECT * FROM clients WHERE id = ?1",
+            rusqlite::params![id],
+            Self::from_row,
+        )
+        .optional()
+    }
+
+    pub fn find_by_name(conn: &Connection, name: &str) -> Result<Option<Self>> {
+        conn.query_row(
+            "SELECT * FROM clients WHERE name = ?1",
+            rusqlite::params![name],
+            Self::from_row,
+        )
+        .optional()
+    }
+
+    pub fn list(conn: &Connection) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare("SELECT * FROM clients ORDER BY name")?;
+        let rows = stmt.query_map([], Self::from_row)?;
+
+        let mut vec: Vec<Client> = Vec::new();
+
+        for row in rows {
+            vec.push(row?);
+        }
+
+        Ok(vec)
+    }
+
+    pub fn save(&mut self, conn: &Connection, clock: &dyn Clock) -> Result<()> {
+        self.updated_at = clock.now();
+        conn.execute(
+            "UPDATE clients SET name = ?1, billable = ?2, currency = ?3, hourly_rate = ?4, updated_at = ?5 WHERE id = ?6",
+            rusqlite::params![
+                self.name,
+                self.billing.billable,
+                self.billing.currency,
+                self.billing.hourly_rate,
+                self.updated_at.to_rfc3339(),
+                self.id
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            billing: Billing::from_row(row)?,
+            created_at: get_datetime(row, "created_at")?,
+            updated_at: get_datetime(row, "updated_at")?,
+        })
+    }
+}
+
+// A project, optionally for a client. Entries whose activity is not part of any project belong
+// to the default one.
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct Project {
+    pub id: i32,
+    pub client_id: Option<i32>,
+    pub name: String,
+    pub is_default: bool,
+    pub billing: Billing,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Project {
+    pub fn create(
+        conn: &Connection,
+        client_id: Option<i32>,
+        name: String,
+        billing: Billing,
+        clock: &dyn Clock,
+    ) -> Result<Self> {
+        let now = clock.now();
+        conn.execute(
+            "INSERT INTO projects (client_id, name, billable, currency, hourly_rate, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)",
+            rusqlite::params![
+                client_id,
+                name,
+                billing.billable,
+                billing.currency,
+                billing.hourly_rate,
+                now.to_rfc3339()
+            ],
+        )?;
+
+        Ok(Project {
+            id: conn.last_insert_rowid() as i32,
+            client_id,
+            name,
+            is_default: false,
+            billing,
+            created_at: now,
+            updated_at: now,
+        })
+    }
+
+    pub fn find(conn: &Connection, id: i32) -> Result<Option<Self>> {
+        conn.query_row(
+            "SELECT * FROM projects WHERE id = ?1",
+            rusqlite::params![id],
+            Self::from_row,
+        )
+        .optional()
+    }
+
+    // Names only need to be unique within a client
+    pub fn find_by_name(
+        conn: &Connection,
+        client_id: Option<i32>,
+        name: &str,
+    ) -> Result<Option<Self>> {
+        conn.query_row(
+            "SELECT * FROM projects WHERE client_id IS ?1 AND name = ?2",
+            rusqlite::params![client_id, name],
+            Self::from_row,
+        )
+        .optional()
+    }
+
+    pub fn list(conn: &Connection) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare("SELECT * FROM projects ORDER BY is_default DESC, name")?;
+        let rows = stmt.query_map([], Self::from_row)?;
+
+        let mut vec: Vec<Project> = Vec::new();
+
+        for row in rows {
+            vec.push(row?);
+        }
+
+        Ok(vec)
+    }
+
+    pub fn save(&mut self, conn: &Connection, clock: &dyn Clock) -> Result<()> {
+        self.updated_at = clock.now();
+        conn.execute(
+            "UPDATE projects SET client_id = ?1, name = ?2, billable = ?3, currency = ?4, hourly_rate = ?5, updated_at = ?6 WHERE id = ?7",
+            rusqlite::params![
+                self.client_id,
+                self.name,
+                self.billing.billable,
+                self.billing.currency,
+                self.billing.hourly_rate,
+                self.updated_at.to_rfc3339(),
+                self.id
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            client_id: row.get("client_id")?,
+            name: row.get("name")?,
+            is_default: row.get("is_default")?,
+            billing: Billing::from_row(row)?,
+            created_at: get_datetime(row, "created_at")?,
+            updated_at: get_datetime(row, "updated_at")?,
+        })
+    }
+}
+
+// Where an activity sits in the client → project → activity tree
+#[derive(Debug, Clone, Serialize, TS)]
+#[ts(export)]
+pub struct ActivityBilling {
+    pub activity_id: i32,
+    pub activity: String,
+    // `None` files it under the default project
+    pub project_id: Option<i32>,
+    pub billing: Billing,
+}
+
+impl ActivityBilling {
+    pub fn find(conn: &Connection, activity_id: i32) -> Result<Option<Self>> {
+        conn.query_row(
+            "SELECT * FROM activities WHERE id = ?1",
+            rusqlite::params![activity_id],
+            Self::from_row,
+        )
+        .optional()
+    }
+
+    pub fn list(conn: &Connection) -> Result<Vec<Self>> {
+        let mut stmt = conn.prepare("SELECT * FROM activities ORDER BY name")?;
+        let rows = stmt.query_map([], Self::from_row)?;
+
+        let mut vec: Vec<ActivityBilling> = Vec::new();
+
+        for row in rows {
+            vec.push(row?);
+        }
+
+        Ok(vec)
+    }
+
+    pub fn save(&self, conn: &Connection, clock: &dyn Clock) -> Result<()> {
+        conn.execute(
+            "UPDATE activities SET project_id = ?1, billable = ?2, currency = ?3, hourly_rate = ?4, updated_at = ?5 WHERE id = ?6",
+            rusqlite::params![
+                self.project_id,
+                self.billing.billable,
+                self.billing.currency,
+                self.billing.hourly_rate,
+                clock.now().to_rfc3339(),
+                self.activity_id
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            activity_id: row.get("id")?,
+            activity: row.get("name")?,
+            project_id: row.get("project_id")?,
+            billing: Billing::from_row(row)?,
+        })
+    }
+}
@@ -0,0 +1,130 @@
+use chrono::{DateTime, Utc};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::notifications;
+use super::pomodoro::{PomodoroPhaseKind, PomodoroTransition};
+use super::service::TimerService;
+use crate::clock::{Clock, OffsetClock};
+use crate::configuration::{Configuration, ConfigurationState};
+use crate::errors::TimersError;
+
+// Upper bound on a sleep, so a clock moved by time travel or a suspended machine is noticed
+// even if nobody wakes the scheduler.
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+// Background thread ending pomodoro phases on time. All state lives in the database, so after a
+// restart the first pass catches up with whatever ran out while the app was closed.
+pub struct PomodoroScheduler {
+    wake: Sender<()>,
+}
+
+impl PomodoroScheduler {
+    pub fn start(app: AppHandle) -> Self {
+        let (wake, wakeups) = mpsc::channel();
+        thread::spawn(move || run(app, wakeups));
+
+        PomodoroScheduler { wake }
+    }
+
+    // Recomputes the next deadline, called after anything that changes the running timer
+    pub fn wake(&self) {
+        if self.wake.send(()).is_err() {
+            log::error!("Pomodoro scheduler is not running");
+        }
+    }
+}
+
+fn run(app: AppHandle, wakeups: Receiver<()>) {
+    log::info!("Pomodoro scheduler started");
+
+    loop {
+        let sleep = match tick(&app) {
+            Ok(Some(deadline)) => {
+                let now = app.state::<OffsetClock>().now();
+                (deadline - now)
+                    .to_std()
+                    .unwrap_or(Duration::ZERO)
+                    .min(MAX_SLEEP)
+            }
+            Ok(None) => MAX_SLEEP,
+            Err(e) => {
+                log::error!("Pomodoro scheduler failed: {}", e);
+                MAX_SLEEP
+            }
+        };
+
+        match wakeups.recv_timeout(sleep) {
+            Ok(()) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+// Applies every transition that is due and returns when the current phase runs out
+fn tick(app: &AppHandle) -> Result<Option<DateTime<Utc>>, TimersError> {
+    let db = app.state::<Pool<SqliteConnectionManager>>();
+    let clock = app.state::<OffsetClock>();
+    // Read on every pass so edited settings apply to the next phase
+    let configuration = app.state::<ConfigurationState>().get();
+
+    let connection = db.get()?;
+    let service = TimerService::new(&connection, clock.inner())
+        .with_pomodoro_settings(configuration.pomodoro.clone())
+        .with_calendar(configuration.calendar());
+
+    while let Some(transition) = service.advance_pomodoro()? {
+        log::info!(
+            "Pomodoro phase {} finished, next: {:?}",
+            transition.finished.id,
+            transition.started.as_ref().map(|p| p.kind)
+        );
+
+        notify(&transition, &configuration);
+
+        if let Err(e) = app.emit("pomodoro-phase-changed", transition) {
+            log::error!("Could not emit pomodoro phase change: {}", e);
+        }
+    }
+
+    service.pomodoro_deadline()
+}
+
+// Sends the notifications turned on for a transition. A notification that can't be shown is
+// only logged, the phase has changed either way.
+fn notify(transition: &PomodoroTransition, configuration: &Configuration) {
+    let toggles = &configuration.notifications;
+
+    let finished = match transition.finished.kind {
+        PomodoroPhaseKind::Work if toggles.timer_finished => {
+            Some(notifications::send_timer_finished_notification())
+        }
+        PomodoroPhaseKind::ShortBreak | PomodoroPhaseKind::LongBreak if toggles.breaks => {
+            Some(notifications::send_break_finished_notification())
+        }
+        _ => None,
+    };
+
+    let started = match transition.started.as_ref().map(|p| p.kind) {
+        Some(PomodoroPhaseKind::Work) if toggles.timer_started => {
+            Some(notifications::send_timer_start_notification())
+        }
+        Some(kind @ (PomodoroPhaseKind::ShortBreak | PomodoroPhaseKind::LongBreak))
+            if toggles.breaks =>
+        {
+            let minutes = kind.length(&configuration.pomodoro).num_minutes() as u32;
+            Some(notifications::send_break_started_notification(minutes))
+        }
+        _ => None,
+    };
+
+    for result in [finished, started].into_iter().flatten() {
+        if let Err(e) = result {
+            log::warn!("Could not send pomodoro notification: {}", e);
+        }
+    }
+}
@@ -0,0 +1,1250 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{Connection, Transaction, TransactionBehavior};
+use std::collections::HashMap;
+
+use super::catalogue::{CatalogueItem, CatalogueKind};
+use super::entries::{EntryFilter, TimeEntryPatch, TimerEntry, TimerStatus};
+use super::pauses::TimerPause;
+use super::pomodoro::{PomodoroPhase, PomodoroPhaseKind, PomodoroTransition};
+use super::projects::{ActivityBilling, Billing, Client, Project};
+use super::statistics::{
+    self, ActivityStatistic, Counter, Earnings, EarningsGrouping, TagStatistic, TimerStatistic,
+};
+use super::tags;
+use crate::clock::{Calendar, Clock};
+use crate::configuration::{ActiveTimerPolicy, OverlapPolicy, PomodoroSettings};
+use crate::errors::TimersError;
+
+// How late a break may be noticed and still roll into the next work session. Anything later
+// means the app was not running, so the cycle stops instead of starting work in the past.
+const BREAK_END_GRACE_SECONDS: i64 = 60;
+
+// Timer operations on top of a plain SQLite connection. Commands borrow a pooled connection
+// and delegate here, tests run it against an in-memory database.
+pub struct TimerService<'a> {
+    conn: &'a Connection,
+    clock: &'a dyn Clock,
+    active_timer_policy: ActiveTimerPolicy,
+    overlap_policy: OverlapPolicy,
+    pomodoro: PomodoroSettings,
+    calendar: Calendar,
+    filter: EntryFilter,
+}
+
+impl<'a> TimerService<'a> {
+    pub fn new(conn: &'a Connection, clock: &'a dyn Clock) -> Self {
+        TimerService {
+            conn,
+            clock,
+            active_timer_policy: ActiveTimerPolicy::default(),
+            overlap_policy: OverlapPolicy::default(),
+            pomodoro: PomodoroSettings::default(),
+            calendar: Calendar::default(),
+            filter: EntryFilter::default(),
+        }
+    }
+
+    pub fn with_active_timer_policy(mut self, active_timer_policy: ActiveTimerPolicy) -> Self {
+        self.active_timer_policy = active_timer_policy;
+        self
+    }
+
+    pub fn with_overlap_policy(mut self, overlap_policy: OverlapPolicy) -> Self {
+        self.overlap_policy = overlap_policy;
+        self
+    }
+
+    pub fn with_pomodoro_settings(mut self, pomodoro: PomodoroSettings) -> Self {
+        self.pomodoro = pomodoro;
+        self
+    }
+
+    pub fn with_calendar(mut self, calendar: Calendar) -> Self {
+        self.calendar = calendar;
+        self
+    }
+
+    // Whether history and activity statistics show cancelled timers
+    pub fn with_cancelled(mut self, include_cancelled: bool) -> Self {
+        self.filter.include_cancelled = include_cancelled;
+        self
+    }
+
+    // Limits history and statistics to entries carrying the tag
+    pub fn with_tag(mut self, tag: Option<String>) -> Self {
+        self.filter.tag = tag;
+        self
+    }
+
+    // Runs a state transition in a single transaction, so `timers` and `timer_statistics`
+    // are either both updated or both left untouched. The write lock is taken up front so two
+    // concurrent starts can't both see "no active timer".
+    fn transaction<T>(
+        &self,
+        operation: impl FnOnce(&Connection) -> Result<T, TimersError>,
+    ) -> Result<T, TimersError> {
+        let tx = Transaction::new_unchecked(self.conn, TransactionBehavior::Immediate)?;
+        let result = operation(&tx)?;
+        tx.commit()?;
+
+        Ok(result)
+    }
+
+    pub fn start(
+        &self,
+        activity: Option<String>,
+        area: Option<String>,
+        tags: Vec<String>,
+    ) -> Result<TimerEntry, TimersError> {
+        self.transaction(|conn| {
+            let now = self.clock.now();
+
+            self.make_room_for_new_timer(conn, now)?;
+            self.end_pomodoro_phase(conn, now)?;
+            let mut timer_entry =
+                TimerEntry::create(activity, area, conn, now, false, &self.calendar, self.clock)?;
+            timer_entry.set_tags(tags, conn, self.clock)?;
+
+            Ok(timer_entry)
+        })
+    }
+
+    pub fn start_pomodoro(
+        &self,
+        activity: Option<String>,
+        tags: Vec<String>,
+    ) -> Result<TimerEntry, TimersError> {
+        self.transaction(|conn| {
+            let now = self.clock.now();
+
+            // Starting during a break carries on with the set, otherwise a new one begins
+            let cycle = match PomodoroPhase::find_current(conn)? {
+                Some(phase) if phase.kind != PomodoroPhaseKind::Work => {
+                    phase.next(&self.pomodoro).1
+                }
+                _ => 1,
+            };
+
+            self.make_room_for_new_timer(conn, now)?;
+            self.end_pomodoro_phase(conn, now)?;
+            self.start_work_phase(conn, activity, tags, cycle, now)
+        })
+    }
+
+    fn start_work_phase(
+        &self,
+        conn: &Connection,
+        activity: Option<String>,
+        tags: Vec<String>,
+        cycle: i32,
+        at: DateTime<Utc>,
+    ) -> Result<TimerEntry, TimersError> {
+        statistics::mark_timer_started(conn, self.calendar.day_of(at), self.clock)?;
+        let mut timer_entry =
+            TimerEntry::create(activity, None, conn, at, true, &self.calendar, self.clock)?;
+        timer_entry.set_tags(tags, conn, self.clock)?;
+        PomodoroPhase::create(
+            conn,
+            PomodoroPhaseKind::Work,
+            cycle,
+            Some(timer_entry.id),
+            at,
+            &self.pomodoro,
+            self.clock,
+        )?;
+
+        Ok(timer_entry)
+    }
+
+    fn end_pomodoro_phase(&self, conn: &Connection, at: DateTime<Utc>) -> Result<(), TimersError> {
+        if let Some(mut phase) = PomodoroPhase::find_current(conn)? {
+            phase.end(at, conn, self.clock)?;
+        }
+
+        Ok(())
+    }
+
+    // Moves the pomodoro cycle on when the current phase has run out: a finished work session
+    // ends its entry and starts a break, a finished break starts the next work session.
+    // Returns `None` when there is nothing to do yet.
+    pub fn advance_pomodoro(&self) -> Result<Option<PomodoroTransition>, TimersError> {
+        self.transaction(|conn| {
+            let now = self.clock.now();
+
+            let mut phase = match PomodoroPhase::find_current(conn)? {
+                Some(phase) if phase.ends_at <= now => phase,
+                _ => return Ok(None),
+            };
+
+            let work_entry = match phase.timer_id {
+                Some(id) => TimerEntry::find(id, conn, self.clock)?,
+                None => None,
+            };
+
+            // A paused session is not due, its end moves back when it resumes
+            if work_entry.as_ref().is_some_and(|t| t.paused_at.is_some()) {
+                return Ok(None);
+            }
+
+            let ended_at = phase.ends_at;
+            phase.end(ended_at, conn, self.clock)?;
+            let (kind, cycle) = phase.next(&self.pomodoro);
+
+            if phase.kind == PomodoroPhaseKind::Work {
+                let timer_entry = match work_entry {
+                    Some(mut t) if t.end_time.is_none() => {
+                        t.end(ended_at, TimerStatus::Finished, conn, self.clock)?;
+                        statistics::mark_timer_finished(
+                            conn,
+                            self.calendar.day_of(ended_at),
+                            self.clock,
+                        )?;
+                        Some(t)
+                    }
+                    other => other,
+                };
+                let started = PomodoroPhase::create(
+                    conn,
+                    kind,
+                    cycle,
+                    None,
+                    ended_at,
+                    &self.pomodoro,
+                    self.clock,
+                )?;
+
+                return Ok(Some(PomodoroTransition {
+                    finished: phase,
+                    started: Some(started),
+                    timer_entry,
+                }));
+            }
+
+            if (now - ended_at).num_seconds() > BREAK_END_GRACE_SECONDS {
+                log::info!("Pomodoro break ended while the app was closed, stopping the cycle");
+                return Ok(Some(PomodoroTransition {
+                    finished: phase,
+                    started: None,
+                    timer_entry: None,
+                }));
+            }
+
+            let last_work = match PomodoroPhase::find_last_work(conn)?.and_then(|w| w.timer_id) {
+                Some(id) => TimerEntry::find(id, conn, self.clock)?,
+                None => None,
+            };
+            let (activity, tags) = match last_work {
+                Some(t) => (t.activity, t.tags),
+                None => (None, Vec::new()),
+            };
+            let timer_entry = self.start_work_phase(conn, activity, tags, cycle, ended_at)?;
+
+            Ok(Some(PomodoroTransition {
+                finished: phase,
+                started: PomodoroPhase::find_current(conn)?,
+                timer_entry: Some(timer_entry),
+            }))
+        })
+    }
+
+    pub fn pomodoro_phase(&self) -> Result<Option<PomodoroPhase>, TimersError> {
+        Ok(PomodoroPhase::find_current(self.conn)?)
+    }
+
+    // When the current phase runs out, or `None` if nothing is scheduled (no cycle running,
+    // or the work session is paused).
+    pub fn pomodoro_deadline(&self) -> Result<Option<DateTime<Utc>>, TimersError> {
+        let phase = match PomodoroPhase::find_current(self.conn)? {
+            Some(phase) => phase,
+            None => return Ok(None),
+        };
+
+        if let Some(id) = phase.timer_id {
+            let paused =
+                TimerEntry::find(id, self.conn, self.clock)?.is_some_and(|t| t.paused_at.is_some());
+            if paused {
+                return Ok(None);
+            }
+        }
+
+        Ok(Some(phase.ends_at))
+    }
+
+    // Only one timer can run at a time, either stop the running one or refuse to start
+    fn make_room_for_new_timer(
+        &self,
+        conn: &Connection,
+        now: DateTime<Utc>,
+    ) -> Result<(), TimersError> {
+        if TimerEntry::get_active_entry(conn, self.clock)?.is_none() {
+            return Ok(());
+        }
+
+        match self.active_timer_policy {
+            ActiveTimerPolicy::StopPrevious => {
+                log::debug!("Stopping running timer before starting a new one");
+                self.finish_active(conn, now)?;
+                Ok(())
+            }
+            ActiveTimerPolicy::Reject => Err(TimersError::Validation(String::from(
+                "A timer is already running, stop it before starting a new one",
+            ))),
+        }
+    }
+
+    // Ends the active timer, counting it as finished when it was a pomodoro
+    pub fn finish(&self) -> Result<Option<TimerEntry>, TimersError> {
+        self.transaction(|conn| self.finish_active(conn, self.clock.now()))
+    }
+
+    fn finish_active(
+        &self,
+        conn: &Connection,
+        now: DateTime<Utc>,
+    ) -> Result<Option<TimerEntry>, TimersError> {
+        self.end_pomodoro_phase(conn, now)?;
+
+        match TimerEntry::get_active_entry(conn, self.clock)? {
+            Some(mut timer_entry) => {
+                timer_entry.end(now, TimerStatus::Finished, conn, self.clock)?;
+
+                if timer_entry.is_pomodoro {
+                    statistics::mark_timer_finished(conn, self.calendar.day_of(now), self.clock)?;
+                }
+
+                Ok(Some(timer_entry))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn cancel(&self) -> Result<Option<TimerEntry>, TimersError> {
+        self.transaction(|conn| {
+            let now = self.clock.now();
+            self.end_pomodoro_phase(conn, now)?;
+
+            match TimerEntry::get_active_entry(conn, self.clock)? {
+                Some(mut timer_entry) => {
+                    timer_entry.end(now, TimerStatus::Cancelled, conn, self.clock)?;
+
+                    if timer_entry.is_pomodoro {
+                        statistics::mark_timer_cancelled(
+                            conn,
+                            self.calendar.day_of(now),
+                            self.clock,
+                        )?;
+                    }
+
+                    Ok(Some(timer_entry))
+                }
+                None => Ok(None),
+            }
+        })
+    }
+
+    // Throws the running timer away, as if it was never started
+    pub fn discard(&self) -> Result<Option<TimerEntry>, TimersError> {
+        self.transaction(|conn| {
+            let now = self.clock.now();
+            self.end_pomodoro_phase(conn, now)?;
+
+            match TimerEntry::get_active_entry(conn, self.clock)? {
+                Some(mut timer_entry) => {
+                    let counted_before = self.counted_days(&timer_entry);
+                    timer_entry.end(now, TimerStatus::Discarded, conn, self.clock)?;
+                    self.recount(conn, counted_before, Vec::new())?;
+
+                    Ok(Some(timer_entry))
+                }
+                None => Ok(None),
+            }
+        })
+    }
+
+    pub fn pause(&self) -> Result<TimerEntry, TimersError> {
+        self.transaction(|conn| {
+            let mut timer_entry = self.running_entry(conn)?;
+
+            if timer_entry.paused_at.is_some() {
+                return Err(TimersError::Validation(String::from(
+                    "The timer is already paused",
+                )));
+            }
+
+            timer_entry.pause(self.clock.now(), conn, self.clock)?;
+            Ok(timer_entry)
+        })
+    }
+
+    pub fn resume(&self) -> Result<TimerEntry, TimersError> {
+        self.transaction(|conn| {
+            let mut timer_entry = self.running_entry(conn)?;
+
+            if timer_entry.paused_at.is_none() {
+                return Err(TimersError::Validation(String::from(
+                    "The timer is not paused",
+                )));
+            }
+
+            let now = self.clock.now();
+            let paused_for = now - timer_entry.paused_at.unwrap_or(now);
+            timer_entry.resume(now, conn, self.clock)?;
+
+            if let Some(mut phase) = PomodoroPhase::find_current(conn)? {
+                if phase.timer_id == Some(timer_entry.id) {
+                    phase.extend(paused_for, conn, self.clock)?;
+                }
+            }
+
+            Ok(timer_entry)
+        })
+    }
+
+    fn running_entry(&self, conn: &Connection) -> Result<TimerEntry, TimersError> {
+        TimerEntry::get_active_entry(conn, self.clock)?
+            .ok_or_else(|| TimersError::NotFound(String::from("Running timer")))
+    }
+
+    // Databases written before only one timer could run may hold several open entries.
+    // Close all but the newest at the moment the following one was started.
+    pub fn repair_open_entries(&self) -> Result<usize, TimersError> {
+        self.transaction(|conn| {
+            let mut open_entries = TimerEntry::get_open_entries(conn, self.clock)?;
+            let next_start_times: Vec<DateTime<Utc>> =
+                open_entries.iter().skip(1).map(|t| t.start_time).collect();
+
+            for (stale, end_time) in open_entries.iter_mut().zip(&next_start_times) {
+                log::info!("Closing stale open time entry {} at {}", stale.id, end_time);
+                let counted_before = self.counted_days(stale);
+                stale.end(*end_time, TimerStatus::Finished, conn, self.clock)?;
+                self.recount(conn, counted_before, self.counted_days(stale))?;
+            }
+
+            Ok(next_start_times.len())
+        })
+    }
+
+    pub fn active(&self) -> Result<Option<TimerEntry>, TimersError> {
+        Ok(TimerEntry::get_active_entry(self.conn, self.clock)?)
+    }
+
+    pub fn update_activity(
+        &self,
+        time_entry_id: i32,
+        activity: String,
+    ) -> Result<TimerEntry, TimersError> {
+        match TimerEntry::find(time_entry_id, self.conn, self.clock)? {
+            Some(mut t) => {
+                t.set_activity(activity, self.conn, self.clock)?;
+                Ok(t)
+            }
+            None => Err(TimersError::NotFound(format!(
+                "Time entry {}",
+                time_entry_id
+            ))),
+        }
+    }
+
+    // Adds a finished entry for time that was not tracked when it happened. Time already
+    // covered by other entries is refused or left out, following the overlap policy.
+    pub fn create_entry(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        activity: Option<String>,
+        area: Option<String>,
+        tags: Vec<String>,
+    ) -> Result<Vec<TimerEntry>, TimersError> {
+        let now = self.clock.now();
+
+        if end_time <= start_time {
+            return Err(TimersError::Validation(String::from(
+                "A time entry has to end after it starts",
+            )));
+        }
+        if end_time > now {
+            return Err(TimersError::Validation(String::from(
+                "A time entry can't end in the future",
+            )));
+        }
+
+        self.transaction(|conn| {
+            let overlapping = TimerEntry::get_overlapping(conn, start_time, end_time, self.clock)?;
+
+            let ranges = match self.overlap_policy {
+                OverlapPolicy::Reject if !overlapping.is_empty() => {
+                    return Err(TimersError::Validation(format!(
+                        "The time entry overlaps {} existing entries",
+                        overlapping.len()
+                    )));
+                }
+                OverlapPolicy::Reject => vec![(start_time, end_time)],
+                OverlapPolicy::Trim | OverlapPolicy::Split => {
+                    free_ranges(start_time, end_time, &overlapping, now)
+                }
+            };
+
+            if ranges.is_empty() {
+                return Err(TimersError::Validation(String::from(
+                    "The whole range is already tracked",
+                )));
+            }
+            if self.overlap_policy == OverlapPolicy::Trim && ranges.len() > 1 {
+                return Err(TimersError::Validation(format!(
+                    "Trimming would leave the time entry in {} pieces, split it instead",
+                    ranges.len()
+                )));
+            }
+
+            let mut created = Vec::new();
+            for (start, end) in ranges {
+                let mut timer_entry = TimerEntry::create_manual(
+                    activity.clone(),
+                    area.clone(),
+                    conn,
+                    start,
+                    end,
+                    &self.calendar,
+                    self.clock,
+                )?;
+                timer_entry.set_tags(tags.clone(), conn, self.clock)?;
+                created.push(timer_entry);
+            }
+
+            Ok(created)
+        })
+    }
+
+    // Applies changes to an entry. The new range must not overlap other entries, and the day
+    // counters of a pomodoro follow it to the days it moved to.
+    pub fn update_entry(
+        &self,
+        time_entry_id: i32,
+        patch: TimeEntryPatch,
+    ) -> Result<TimerEntry, TimersError> {
+        self.transaction(|conn| {
+            let mut timer_entry = TimerEntry::find(time_entry_id, conn, self.clock)?
+                .ok_or_else(|| TimersError::NotFound(format!("Time entry {}", time_entry_id)))?;
+            let now = self.clock.now();
+            let running = timer_entry.end_time.is_none();
+
+            if running && patch.end_time.is_some() {
+                return Err(TimersError::Validation(String::from(
+                    "The timer is still running, finish it to set its end time",
+                )));
+            }
+            if running && patch.is_pomodoro.is_some() {
+                return Err(TimersError::Validation(String::from(
+                    "The timer is still running, finish it to change whether it is a pomodoro",
+                )));
+            }
+
+            let start_time = patch.start_time.unwrap_or(timer_entry.start_time);
+            let end_time = patch.end_time.or(timer_entry.end_time);
+
+            if end_time.is_some_and(|end| end <= start_time) {
+                return Err(TimersError::Validation(String::from(
+                    "A time entry has to end after it starts",
+                )));
+            }
+            if end_time.unwrap_or(start_time) > now {
+                return Err(TimersError::Validation(String::from(
+                    "A time entry can't be moved into the future",
+                )));
+            }
+
+            let overlapping =
+                TimerEntry::get_overlapping(conn, start_time, end_time.unwrap_or(now), self.clock)?
+                    .into_iter()
+                    .filter(|t| t.id != timer_entry.id)
+                    .count();
+            if overlapping > 0 {
+                return Err(TimersError::Validation(format!(
+                    "The time entry would overlap {} other entries",
+                    overlapping
+                )));
+            }
+
+            let counted_before = self.counted_days(&timer_entry);
+
+            if let Some(activity) = patch.activity {
+                timer_entry.assign_activity(Some(activity), conn, self.clock)?;
+            }
+            if let Some(area) = patch.area {
+                timer_entry.assign_area(Some(area), conn, self.clock)?;
+            }
+            if let Some(tags) = patch.tags {
+                timer_entry.set_tags(tags, conn, self.clock)?;
+            }
+            if let Some(is_pomodoro) = patch.is_pomodoro {
+                timer_entry.is_pomodoro = is_pomodoro;
+            }
+            // The countdown of a running pomodoro moves along with its start
+            if running && start_time != timer_entry.start_time {
+                if let Some(mut phase) = PomodoroPhase::find_current(conn)?
+                    .filter(|p| p.timer_id == Some(timer_entry.id))
+                {
+                    phase.shift(start_time - timer_entry.start_time, conn, self.clock)?;
+                }
+            }
+            timer_entry.set_range(start_time, end_time, conn, self.clock)?;
+            timer_entry.save(conn, self.clock)?;
+
+            let counted_after = self.counted_days(&timer_entry);
+            self.recount(conn, counted_before, counted_after)?;
+
+            Ok(timer_entry)
+        })
+    }
+
+    // Cuts an entry in two at `at`. The second part continues with `new_activity`, or the same
+    // activity when there is none, and takes over the pauses after the cut. Both parts keep the
+    // tags. A running timer keeps running as the second part.
+    pub fn split_entry(
+        &self,
+        time_entry_id: i32,
+        at: DateTime<Utc>,
+        new_activity: Option<String>,
+    ) -> Result<Vec<TimerEntry>, TimersError> {
+        self.transaction(|conn| {
+            let mut first = TimerEntry::find(time_entry_id, conn, self.clock)?
+                .ok_or_else(|| TimersError::NotFound(format!("Time entry {}", time_entry_id)))?;
+            let now = self.clock.now();
+            let end_time = first.end_time;
+
+            if at <= first.start_time || at >= end_time.unwrap_or(now) {
+                return Err(TimersError::Validation(String::from(
+                    "The split point has to be inside the time entry",
+                )));
+            }
+            if end_time.is_none() && first.is_pomodoro {
+                return Err(TimersError::Validation(String::from(
+                    "The pomodoro is still running, finish it before splitting it",
+                )));
+            }
+
+            let counted_before = self.counted_days(&first);
+
+            // Only the first part stays a pomodoro, it is the one that was started as one
+            let mut second = first.clone();
+            if let Some(activity) = new_activity.filter(|a| !a.trim().is_empty()) {
+                second.assign_activity(Some(activity), conn, self.clock)?;
+            }
+            second.start_time = at;
+            second.is_pomodoro = false;
+            second.paused_at = first.paused_at.map(|paused_at| paused_at.max(at));
+            second.created_at = now;
+            second.updated_at = now;
+            second.refresh_utc_offset();
+            second.insert(conn)?;
+            tags::copy(conn, first.id, second.id)?;
+            TimerPause::split_at(conn, first.id, second.id, at, self.clock)?;
+
+            // A running timer stops at the cut, the second part carries on in its place
+            first.paused_at = None;
+            if end_time.is_none() {
+                first.status = TimerStatus::Finished;
+            }
+            first.set_range(first.start_time, Some(at), conn, self.clock)?;
+            first.save(conn, self.clock)?;
+            second.set_range(at, end_time, conn, self.clock)?;
+            second.save(conn, self.clock)?;
+
+            let counted_after = self.counted_days(&first);
+            self.recount(conn, counted_before, counted_after)?;
+
+            Ok(vec![first, second])
+        })
+    }
+
+    // Joins entries of the same activity that follow each other into the earliest one. Time
+    // between them becomes a pause, so the merged duration is what was tracked. The merged entry
+    // carries the tags of all of them.
+    pub fn merge_entries(&self, time_entry_ids: Vec<i32>) -> Result<TimerEntry, TimersError> {
+        let mut time_entry_ids = time_entry_ids;
+        time_entry_ids.sort_unstable();
+        time_entry_ids.dedup();
+
+        if time_entry_ids.len() < 2 {
+            return Err(TimersError::Validation(String::from(
+                "Pick at least two time entries to merge",
+            )));
+        }
+
+        self.transaction(|conn| {
+            let mut entries = Vec::new();
+            for &id in &time_entry_ids {
+                entries.push(
+                    TimerEntry::find(id, conn, self.clock)?
+                        .ok_or_else(|| TimersError::NotFound(format!("Time entry {}", id)))?,
+                );
+            }
+            entries.sort_by_key(|t| t.start_time);
+
+            let (first, rest) = entries.split_first().expect("at least two entries");
+            let (last, earlier) = entries.split_last().expect("at least two entries");
+
+            if earlier.iter().any(|t| t.end_time.is_none()) {
+                return Err(TimersError::Validation(String::from(
+                    "Only the latest entry can still be running",
+                )));
+            }
+
+            if rest.iter().any(|t| t.activity_id != first.activity_id) {
+                return Err(TimersError::Validation(String::from(
+                    "Only entries with the same activity can be merged",
+                )));
+            }
+            if rest.iter().any(|t| t.is_pomodoro != first.is_pomodoro) {
+                return Err(TimersError::Validation(String::from(
+                    "Pomodoros can't be merged with other timers",
+                )));
+            }
+            // A running entry takes the status of the merged ones when it ends
+            if rest
+                .iter()
+                .any(|t| t.end_time.is_some() && t.status != first.status)
+            {
+                return Err(TimersError::Validation(String::from(
+                    "Finished and cancelled entries can't be merged",
+                )));
+            }
+
+            let in_between = TimerEntry::get_overlapping(
+                conn,
+                first.start_time,
+                last.end_time.unwrap_or_else(|| self.clock.now()),
+                self.clock,
+            )?
+            .into_iter()
+            .filter(|t| !time_entry_ids.contains(&t.id))
+            .count();
+            if in_between > 0 {
+                return Err(TimersError::Validation(format!(
+                    "Only adjacent entries can be merged, {} other entries are in between",
+                    in_between
+                )));
+            }
+
+            let counted_before: Vec<_> =
+                entries.iter().flat_map(|t| self.counted_days(t)).collect();
+
+            let mut merged = first.clone();
+            for pair in entries.windows(2) {
+                let gap_start = pair[0].end_time.expect("earlier entries have ended");
+                if pair[1].start_time > gap_start {
+                    TimerPause::create_ended(
+                        conn,
+                        merged.id,
+                        gap_start,
+                        pair[1].start_time,
+                        self.clock,
+                    )?;
+                }
+            }
+            for timer_entry in rest {
+                timer_entry.merge_into(merged.id, conn)?;
+                if merged.area_id.is_none() {
+                    merged.area_id = timer_entry.area_id;
+                    merged.area = timer_entry.area.clone();
+                }
+                merged.is_manual |= timer_entry.is_manual;
+            }
+
+            merged.paused_at = last.paused_at;
+            merged.status = last.status;
+            merged.tags = tags::for_timer(conn, merged.id)?;
+            merged.set_range(merged.start_time, last.end_time, conn, self.clock)?;
+            merged.save(conn, self.clock)?;
+
+            let counted_after = self.counted_days(&merged);
+            self.recount(conn, counted_before, counted_after)?;
+
+            Ok(merged)
+        })
+    }
+
+    // Moves a finished entry to the trash, where it stays out of history and statistics until
+    // it is restored or purged. The day counters keep it, the timer was still started.
+    pub fn delete_entry(&self, time_entry_id: i32) -> Result<TimerEntry, TimersError> {
+        self.transaction(|conn| {
+            let mut timer_entry = TimerEntry::find(time_entry_id, conn, self.clock)?
+                .ok_or_else(|| TimersError::NotFound(format!("Time entry {}", time_entry_id)))?;
+
+            if timer_entry.end_time.is_none() {
+                return Err(TimersError::Validation(String::from(
+                    "The timer is still running, cancel it instead",
+                )));
+            }
+
+            timer_entry.set_deleted_at(Some(self.clock.now()), conn, self.clock)?;
+            Ok(timer_entry)
+        })
+    }
+
+    pub fn restore_entry(&self, time_entry_id: i32) -> Result<TimerEntry, TimersError> {
+        self.transaction(|conn| {
+            let mut timer_entry = TimerEntry::find_deleted(time_entry_id, conn, self.clock)?
+                .ok_or_else(|| {
+                    TimersError::NotFound(format!("Deleted time entry {}", time_entry_id))
+                })?;
+
+            // The time may have been tracked again since
+            let end_time = timer_entry.end_time.unwrap_or_else(|| self.clock.now());
+            let overlapping =
+                TimerEntry::get_overlapping(conn, timer_entry.start_time, end_time, self.clock)?;
+            if !overlapping.is_empty() {
+                return Err(TimersError::Validation(format!(
+                    "Restoring the time entry would overlap {} other entries",
+                    overlapping.len()
+                )));
+            }
+
+            timer_entry.set_deleted_at(None, conn, self.clock)?;
+            Ok(timer_entry)
+        })
+    }
+
+    pub fn trash(&self) -> Result<Vec<TimerEntry>, TimersError> {
+        Ok(TimerEntry::get_deleted(self.conn, self.clock)?)
+    }
+
+    // Removes a deleted entry for good, or every deleted entry when no id is given
+    pub fn purge(&self, time_entry_id: Option<i32>) -> Result<usize, TimersError> {
+        self.transaction(|conn| {
+            let purged = TimerEntry::purge(conn, time_entry_id)?;
+
+            match time_entry_id {
+                Some(id) if purged == 0 => {
+                    Err(TimersError::NotFound(format!("Deleted time entry {}", id)))
+                }
+                _ => Ok(purged),
+            }
+        })
+    }
+
+    pub fn catalogue(
+        &self,
+        kind: CatalogueKind,
+        include_archived: bool,
+    ) -> Result<Vec<CatalogueItem>, TimersError> {
+        Ok(CatalogueItem::list(self.conn, kind, include_archived)?)
+    }
+
+    // Renames an activity or area on every entry filed under it. Taking the name of another one
+    // is refused, that is what merging is for.
+    pub fn rename_catalogue_item(
+        &self,
+        kind: CatalogueKind,
+        id: i32,
+        name: String,
+    ) -> Result<CatalogueItem, TimersError> {
+        let name = required_name(name, kind.label())?;
+
+        self.transaction(|conn| {
+            let mut item = self.find_catalogue_item(conn, kind, id)?;
+
+            if let Some(existing) = CatalogueItem::find_by_name(conn, kind, &name)? {
+                if existing.id != id {
+                    return Err(TimersError::Validation(format!(
+                        "{} {:?} already exists, merge into it instead",
+                        kind.label(),
+                        existing.name
+                    )));
+                }
+            }
+
+            item.rename(kind, name, conn, self.clock)?;
+
+            Ok(item)
+        })
+    }
+
+    // Files every entry of `source_id` under `target_id` and removes the source
+    pub fn merge_catalogue_items(
+        &self,
+        kind: CatalogueKind,
+        source_id: i32,
+        target_id: i32,
+    ) -> Result<CatalogueItem, TimersError> {
+        if source_id == target_id {
+            return Err(TimersError::Validation(format!(
+                "An {} can't be merged into itself",
+                kind.label().to_lowercase()
+            )));
+        }
+
+        self.transaction(|conn| {
+            let source = self.find_catalogue_item(conn, kind, source_id)?;
+            let target = self.find_catalogue_item(conn, kind, target_id)?;
+
+            let moved = source.merge_into(kind, target.id, conn)?;
+            log::info!(
+                "Merged {} {:?} into {:?}, {} entries moved",
+                kind.label().to_lowercase(),
+                source.name,
+                target.name,
+                moved
+            );
+
+            Ok(target)
+        })
+    }
+
+    // Archives an activity or area, or brings it back with `archived` false
+    pub fn set_catalogue_item_archived(
+        &self,
+        kind: CatalogueKind,
+        id: i32,
+        archived: bool,
+    ) -> Result<CatalogueItem, TimersError> {
+        self.transaction(|conn| {
+            let mut item = self.find_catalogue_item(conn, kind, id)?;
+            let archived_at = archived.then(|| item.archived_at.unwrap_or(self.clock.now()));
+            item.set_archived_at(kind, archived_at, conn, self.clock)?;
+
+            Ok(item)
+        })
+    }
+
+    fn find_catalogue_item(
+        &self,
+        conn: &Connection,
+        kind: CatalogueKind,
+        id: i32,
+    ) -> Result<CatalogueItem, TimersError> {
+        CatalogueItem::find(conn, kind, id)?
+            .ok_or_else(|| TimersError::NotFound(format!("{} {}", kind.label(), id)))
+    }
+
+    // Moves the day counters from where an entry was counted to where it is counted now
+    fn recount(
+        &self,
+        conn: &Connection,
+        counted_before: Vec<(NaiveDate, Counter)>,
+        counted_after: Vec<(NaiveDate, Counter)>,
+    ) -> Result<(), TimersError> {
+        if counted_before == counted_after {
+            return Ok(());
+        }
+
+        for (day, counter) in counted_before {
+            statistics::adjust_counter(conn, day, counter, -1, self.clock)?;
+        }
+        for (day, counter) in counted_after {
+            statistics::adjust_counter(conn, day, counter, 1, self.clock)?;
+        }
+
+        Ok(())
+    }
+
+    // The day counters a pomodoro entry is added to: started on the day it started, finished
+    // or cancelled on the day it ended
+    fn counted_days(&self, timer_entry: &TimerEntry) -> Vec<(NaiveDate, Counter)> {
+        if !timer_entry.is_pomodoro || timer_entry.status == TimerStatus::Discarded {
+            return Vec::new();
+        }
+
+        let mut counted = vec![(
+            self.calendar.day_of(timer_entry.start_time),
+            Counter::Started,
+        )];
+        if let Some(end_time) = timer_entry.end_time {
+            let counter = match timer_entry.status {
+                TimerStatus::Cancelled => Counter::Cancelled,
+                _ => Counter::Finished,
+            };
+            counted.push((self.calendar.day_of(end_time), counter));
+        }
+
+        counted
+    }
+
+    pub fn history(&self) -> Result<Vec<TimerEntry>, TimersError> {
+        Ok(TimerEntry::get_history(
+            self.conn,
+            &self.filter,
+            self.clock,
+        )?)
+    }
+
+    pub fn history_by_date(&self) -> Result<HashMap<NaiveDate, Vec<TimerEntry>>, TimersError> {
+        Ok(TimerEntry::get_history_by_date(
+            self.conn,
+            &self.calendar,
+            &self.filter,
+            self.clock,
+        )?)
+    }
+
+    pub fn daily_statistics(&self) -> Result<TimerStatistic, TimersError> {
+        Ok(statistics::load_statistic_for_date(
+            self.conn,
+            self.today(),
+            self.clock,
+        )?)
+    }
+
+    pub fn statistics_history(&self, days: i64) -> Result<Vec<TimerStatistic>, TimersError> {
+        Ok(TimerStatistic::load_statistics_history(
+            self.conn,
+            self.today(),
+            days,
+        )?)
+    }
+
+    pub fn activity_statistics(&self) -> Result<Vec<ActivityStatistic>, TimersError> {
+        Ok(ActivityStatistic::load_activity_statistics_for_date(
+            self.conn,
+            self.today(),
+            &self.calendar,
+            &self.filter,
+        )?)
+    }
+
+    pub fn tag_statistics(&self) -> Result<Vec<TagStatistic>, TimersError> {
+        Ok(TagStatistic::load_tag_statistics_for_date(
+            self.conn,
+            self.today(),
+            &self.calendar,
+            &self.filter,
+        )?)
+    }
+
+    pub fn tags(&self) -> Result<Vec<String>, TimersError> {
+        Ok(tags::list(self.conn)?)
+    }
+
+    // Earnings of the days from `from` to `to`, both included
+    pub fn earnings(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        grouping: EarningsGrouping,
+    ) -> Result<Vec<Earnings>, TimersError> {
+        if to < from {
+            return Err(TimersError::Validation(String::from(
+                "The last day can't be before the first one",
+            )));
+        }
+
+        Ok(Earnings::load_earnings(
+            self.conn,
+            from,
+            to,
+            grouping,
+            &self.calendar,
+            &self.filter,
+        )?)
+    }
+
+    pub fn clients(&self) -> Result<Vec<Client>, TimersError> {
+        Ok(Client::list(self.conn)?)
+    }
+
+    pub fn create_client(&self, name: String, billing: Billing) -> Result<Client, TimersError> {
+        let name = required_name(name, "Client")?;
+        let billing = checked_billing(billing)?;
+
+        self.transaction(|conn| {
+            if Client::find_by_name(conn, &name)?.is_some() {
+                return Err(TimersError::Validation(format!(
+                    "Client {:?} already exists",
+                    name
+                )));
+            }
+
+            Ok(Client::create(conn, name, billing, self.clock)?)
+        })
+    }
+
+    pub fn update_client(
+        &self,
+        client_id: i32,
+        name: String,
+        billing: Billing,
+    ) -> Result<Client, TimersError> {
+        let name = required_name(name, "Client")?;
+        let billing = checked_billing(billing)?;
+
+        self.transaction(|conn| {
+            let mut client = Client::find(conn, client_id)?
+                .ok_or_else(|| TimersError::NotFound(format!("Client {}", client_id)))?;
+
+            if Client::find_by_name(conn, &name)?.is_some_and(|c| c.id != client_id) {
+                return Err(TimersError::Validation(format!(
+                    "Client {:?} already exists",
+                    name
+                )));
+            }
+
+            client.name = name;
+            client.billing = billing;
+            client.save(conn, self.clock)?;
+
+            Ok(client)
+        })
+    }
+
+    pub fn projects(&self) -> Result<Vec<Project>, TimersError> {
+        Ok(Project::list(self.conn)?)
+    }
+
+    pub fn create_project(
+        &self,
+        client_id: Option<i32>,
+        name: String,
+        billing: Billing,
+    ) -> Result<Project, TimersError> {
+        let name = required_name(name, "Project")?;
+        let billing = checked_billing(billing)?;
+
+        self.transaction(|conn| {
+            self.check_project_name(conn, None, client_id, &name)?;
+
+            Ok(Project::create(conn, client_id, name, billing, self.clock)?)
+        })
+    }
+
+    pub fn update_project(
+        &self,
+        project_id: i32,
+        client_id: Option<i32>,
+        name: String,
+        billing: Billing,
+    ) -> Result<Project, TimersError> {
+        let name = required_name(name, "Project")?;
+        let billing = checked_billing(billing)?;
+
+        self.transaction(|conn| {
+            let mut project = Project::find(conn, project_id)?
+                .ok_or_else(|| TimersError::NotFound(format!("Project {}", project_id)))?;
+            self.check_project_name(conn, Some(project_id), client_id, &name)?;
+
+            project.client_id = client_id;
+            project.name = name;
+            project.billing = billing;
+            project.save(conn, self.clock)?;
+
+            Ok(project)
+        })
+    }
+
+    // The client has to exist, and no other project of it can have the name
+    fn check_project_name(
+        &self,
+        conn: &Connection,
+        project_id: Option<i32>,
+        client_id: Option<i32>,
+        name: &str,
+    ) -> Result<(), TimersError> {
+        if let Some(client_id) = client_id {
+            Client::find(conn, client_id)?
+                .ok_or_else(|| TimersError::NotFound(format!("Client {}", client_id)))?;
+        }
+
+        match Project::find_by_name(conn, client_id, name)? {
+            Some(existing) if Some(existing.id) != project_id => Err(TimersError::Validation(
+                format!("Project {:?} already exists", name),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn activity_billing(&self) -> Result<Vec<ActivityBilling>, TimersError> {
+        Ok(ActivityBilling::list(self.conn)?)
+    }
+
+    // Files an activity, and every entry of it, under a project. `None` moves it back to the
+    // default project.
+    pub fn assign_activity(
+        &self,
+        activity_id: i32,
+        project_id: Option<i32>,
+        billing: Billing,
+    ) -> Result<ActivityBilling, TimersError> {
+        let billing = checked_billing(billing)?;
+
+        self.transaction(|conn| {
+            let mut activity = ActivityBilling::find(conn, activity_id)?
+                .ok_or_else(|| TimersError::NotFound(format!("Activity {}", activity_id)))?;
+            if let Some(project_id) = project_id {
+                Project::find(conn, project_id)?
+                    .ok_or_else(|| TimersError::NotFound(format!("Project {}", project_id)))?;
+            }
+
+            activity.project_id = project_id;
+            activity.billing = billing;
+            activity.save(conn, self.clock)?;
+
+            Ok(activity)
+        })
+    }
+
+    fn today(&self) -> NaiveDate {
+        self.calendar.day_of(self.clock.now())
+    }
+}
+
+fn required_name(name: String, label: &str) -> Result<String, TimersError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(TimersError::Validation(format!(
+            "{} names can't be empty",
+            label
+        )));
+    }
+
+    Ok(name.to_string())
+}
+
+// Currencies are three letter codes, stored upper case. Rates can't be negative.
+fn checked_billing(billing: Billing) -> Result<Billing, TimersError> {
+    let currency = match billing.currency.as_deref().map(str::trim) {
+        Some(code) if code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic()) => {
+            Some(code.to_ascii_uppercase())
+        }
+        Some(code) => {
+            return Err(TimersError::Validation(format!(
+                "{:?} is not a currency code",
+                code
+            )))
+        }
+        None => None,
+    };
+
+    if billing.hourly_rate.is_some_and(|rate| rate < 0) {
+        return Err(TimersError::Validation(String::from(
+            "Hourly rates can't be negative",
+        )));
+    }
+
+    Ok(Billing {
+        currency,
+        ..billing
+    })
+}
+
+// The parts of `start..end` not covered by any of the entries, which come sorted by start time
+fn free_ranges(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    entries: &[TimerEntry],
+    now: DateTime<Utc>,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let mut ranges = Vec::new();
+    let mut cursor = start;
+
+    for entry in entries {
+        if entry.start_time > cursor {
+            ranges.push((cursor, entry.start_time.min(end)));
+        }
+        cursor = cursor.max(entry.end_time.unwrap_or(now));
+    }
+
+    if cursor < end {
+        ranges.push((cursor, end));
+    }
+
+    ranges
+}
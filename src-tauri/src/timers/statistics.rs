@@ -1,26 +1,120 @@
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
 use rusqlite::{Connection, OptionalExtension, Result, Row};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use ts_rs::TS;
 
-#[derive(Serialize, Deserialize, Debug)]
+use super::entries::EntryFilter;
+use crate::clock::{Calendar, Clock};
+use crate::configuration::DayGrouping;
+use crate::storage::{get_datetime, parse_datetime};
+
+#[derive(Serialize, Deserialize, Debug, TS)]
+#[ts(export)]
 pub struct ActivityStatistic {
-    pub activity: String,
+    pub activity_id: Option<i32>,
+    pub activity: Option<String>,
     pub total_duration: i32,
     pub total_timers: i32,
 }
 
+// Entries counted towards a day: the ones that started on it, in the zone they were started in
+// unless the calendar groups by the current zone. Parameters are the ones of `day_params`.
+const ENTRIES_OF_DAY: &str = "timers.deleted_at IS NULL AND timers.status != 'discarded'
+    AND (?6 OR timers.status != 'cancelled')
+    AND (?7 IS NULL OR EXISTS (
+        SELECT 1 FROM timer_tags ft JOIN tags f ON f.id = ft.tag_id
+        WHERE ft.timer_id = timers.id AND f.name = ?7
+    ))
+    AND CASE WHEN ?3 AND timers.utc_offset IS NOT NULL
+        THEN date(timers.start_time, (timers.utc_offset - ?4) || ' seconds') = ?5
+        ELSE julianday(timers.start_time) >= julianday(?1) AND julianday(timers.start_time) < julianday(?2)
+    END";
+
+fn day_params(
+    day: NaiveDate,
+    calendar: &Calendar,
+    filter: &EntryFilter,
+) -> (String, String, bool, u32, String, bool, Option<String>) {
+    let (start, end) = calendar.bounds(day);
+
+    (
+        start.to_rfc3339(),
+        end.to_rfc3339(),
+        calendar.grouping() == DayGrouping::EntryZone,
+        calendar.start_hour() * 3600,
+        date_string(day),
+        filter.include_cancelled,
+        filter.tag.clone(),
+    )
+}
+
 impl ActivityStatistic {
     pub fn load_activity_statistics_for_date(
         conn: &Connection,
-        date: DateTime<Utc>,
+        day: NaiveDate,
+        calendar: &Calendar,
+        filter: &EntryFilter,
     ) -> Result<Vec<ActivityStatistic>> {
-        let date_string = date.format("%Y-%m-%d").to_string();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT timers.activity_id, a.name, SUM(timers.duration) as total_duration,
+                COUNT(*) as total_timers
+            FROM timers
+            LEFT JOIN activities a ON a.id = timers.activity_id
+            WHERE {}
+            GROUP BY timers.activity_id",
+            ENTRIES_OF_DAY
+        ))?;
+
+        let rows = stmt.query_map(day_params(day, calendar, filter), Self::from_row)?;
 
-        let mut stmt = conn.prepare(
-            "SELECT activity, SUM(duration) as total_duration, COUNT(*) as total_timers FROM timers WHERE date(start_time) = date(?1) GROUP BY activity",
-        )?;
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
 
-        let rows = stmt.query_map(rusqlite::params![date_string], Self::from_row)?;
+        Ok(result)
+    }
+
+    fn from_row(row: &Row) -> Result<Self> {
+        Ok(Self {
+            activity_id: row.get(0)?,
+            activity: row.get(1)?,
+            total_duration: row.get(2)?,
+            total_timers: row.get(3)?,
+        })
+    }
+}
+
+// Time spent on a tag during a day. An entry with several tags counts towards each of them,
+// untagged entries towards none.
+#[derive(Serialize, Deserialize, Debug, TS)]
+#[ts(export)]
+pub struct TagStatistic {
+    pub tag: String,
+    pub total_duration: i32,
+    pub total_timers: i32,
+}
+
+impl TagStatistic {
+    pub fn load_tag_statistics_for_date(
+        conn: &Connection,
+        day: NaiveDate,
+        calendar: &Calendar,
+        filter: &EntryFilter,
+    ) -> Result<Vec<TagStatistic>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT t.name, SUM(timers.duration) as total_duration, COUNT(*) as total_timers
+            FROM timers
+            JOIN timer_tags tt ON tt.timer_id = timers.id
+            JOIN tags t ON t.id = tt.tag_id
+            WHERE {}
+            GROUP BY t.id
+            ORDER BY t.name",
+            ENTRIES_OF_DAY
+        ))?;
+
+        let rows = stmt.query_map(day_params(day, calendar, filter), Self::from_row)?;
 
         let mut result = Vec::new();
         for row in rows {
@@ -32,14 +126,168 @@ impl ActivityStatistic {
 
     fn from_row(row: &Row) -> Result<Self> {
         Ok(Self {
-            activity: row.get(0)?,
+            tag: row.get(0)?,
             total_duration: row.get(1)?,
             total_timers: row.get(2)?,
         })
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub enum EarningsGrouping {
+    #[default]
+    Day,
+    // Weeks start on Monday
+    Week,
+    Client,
+}
+
+// Time tracked and money earned in a day, a week or for a client, in one currency
+#[derive(Debug, PartialEq, Serialize, TS)]
+#[ts(export)]
+pub struct Earnings {
+    // First day of the period, not set when grouping by client
+    pub day: Option<NaiveDate>,
+    // Only set when grouping by client, and not for projects without one
+    pub client_id: Option<i32>,
+    pub client: Option<String>,
+    pub currency: Option<String>,
+    pub total_duration: i32,
+    pub billable_duration: i32,
+    // In hundredths of the currency
+    #[ts(type = "number")]
+    pub amount: i64,
+}
+
+// Period, client and currency
+type EarningsKey = (Option<NaiveDate>, Option<i32>, Option<String>);
+
+// An ended entry with the billing terms it inherits
+struct BilledEntry {
+    start_time: DateTime<Utc>,
+    utc_offset: Option<i32>,
+    duration: i32,
+    client_id: Option<i32>,
+    client: Option<String>,
+    billable: bool,
+    currency: Option<String>,
+    hourly_rate: Option<i32>,
+}
+
+impl Earnings {
+    // Earnings of the days from `from` to `to`, both included. Only ended entries count, with
+    // their stored duration.
+    pub fn load_earnings(
+        conn: &Connection,
+        from: NaiveDate,
+        to: NaiveDate,
+        grouping: EarningsGrouping,
+        calendar: &Calendar,
+        filter: &EntryFilter,
+    ) -> Result<Vec<Earnings>> {
+        let (start, _) = calendar.bounds(from);
+        let (_, end) = calendar.bounds(to);
+
+        // Entries recorded in another zone may belong to a day just outside the range, they are
+        // fetched with a day to spare and sorted out by the calendar
+        let mut stmt = conn.prepare(
+            "SELECT timers.start_time, timers.utc_offset, timers.duration,
+                p.client_id, c.name AS client,
+                COALESCE(a.billable, p.billable, c.billable, 0) AS billable,
+                COALESCE(a.currency, p.currency, c.currency) AS currency,
+                COALESCE(a.hourly_rate, p.hourly_rate, c.hourly_rate) AS hourly_rate
+            FROM timers
+            LEFT JOIN activities a ON a.id = timers.activity_id
+            JOIN projects p ON p.id = COALESCE(a.project_id, (SELECT id FROM projects WHERE is_default))
+            LEFT JOIN clients c ON c.id = p.client_id
+            WHERE timers.end_time IS NOT NULL AND timers.deleted_at IS NULL
+                AND timers.status != 'discarded' AND (?3 OR timers.status != 'cancelled')
+                AND (?4 IS NULL OR EXISTS (
+                    SELECT 1 FROM timer_tags ft JOIN tags f ON f.id = ft.tag_id
+                    WHERE ft.timer_id = timers.id AND f.name = ?4
+                ))
+                AND julianday(timers.start_time) >= julianday(?1) - 1
+                AND julianday(timers.start_time) < julianday(?2) + 1",
+        )?;
+        let rows = stmt.query_map(
+            rusqlite::params![
+                start.to_rfc3339(),
+                end.to_rfc3339(),
+                filter.include_cancelled,
+                filter.tag
+            ],
+            |row| {
+                Ok(BilledEntry {
+                    start_time: get_datetime(row, "start_time")?,
+                    utc_offset: row.get("utc_offset")?,
+                    duration: row.get("duration")?,
+                    client_id: row.get("client_id")?,
+                    client: row.get("client")?,
+                    billable: row.get("billable")?,
+                    currency: row.get("currency")?,
+                    hourly_rate: row.get("hourly_rate")?,
+                })
+            },
+        )?;
+
+        // Rates are per hour, amounts are summed in rate-seconds and rounded once per group
+        let mut groups: BTreeMap<EarningsKey, (Earnings, i64)> = BTreeMap::new();
+
+        for row in rows {
+            let entry = row?;
+            let day = calendar.entry_day(entry.start_time, entry.utc_offset);
+            if day < from || day > to {
+                continue;
+            }
+
+            let (day, client_id, client) = match grouping {
+                EarningsGrouping::Day => (Some(day), None, None),
+                EarningsGrouping::Week => {
+                    let monday = day - Duration::days(day.weekday().num_days_from_monday().into());
+                    (Some(monday), None, None)
+                }
+                EarningsGrouping::Client => (None, entry.client_id, entry.client),
+            };
+
+            let (earnings, rate_seconds) = groups
+                .entry((day, client_id, entry.currency.clone()))
+                .or_insert_with(|| {
+                    (
+                        Earnings {
+                            day,
+                            client_id,
+                            client,
+                            currency: entry.currency,
+                            total_duration: 0,
+                            billable_duration: 0,
+                            amount: 0,
+                        },
+                        0,
+                    )
+                });
+
+            earnings.total_duration += entry.duration;
+            if entry.billable {
+                earnings.billable_duration += entry.duration;
+                *rate_seconds +=
+                    i64::from(entry.duration) * i64::from(entry.hourly_rate.unwrap_or(0));
+            }
+        }
+
+        Ok(groups
+            .into_values()
+            .map(|(mut earnings, rate_seconds)| {
+                earnings.amount = (rate_seconds + 1800) / 3600;
+                earnings
+            })
+            .collect())
+    }
+}
+
+#[derive(Debug, Serialize, TS)]
+#[ts(export)]
 pub struct TimerStatistic {
     pub id: i32,
     pub date_string: String,
@@ -51,20 +299,20 @@ pub struct TimerStatistic {
 }
 
 impl TimerStatistic {
-    pub fn new(date_string: String) -> Self {
+    pub fn new(date_string: String, clock: &dyn Clock) -> Self {
         TimerStatistic {
             id: 0,
-            date_string: date_string,
+            date_string,
             timers_started: 0,
             timers_finished: 0,
             timers_cancelled: 0,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
+            created_at: clock.now(),
+            updated_at: clock.now(),
         }
     }
 
     // Find or create a record in the database with the given date_string
-    pub fn find_or_create(conn: &Connection, date_string: &str) -> Result<Self> {
+    pub fn find_or_create(conn: &Connection, date_string: &str, clock: &dyn Clock) -> Result<Self> {
         let row: Option<Self> = conn
             .query_row(
                 "SELECT * FROM timer_statistics WHERE date_string = ?1",
@@ -75,12 +323,16 @@ impl TimerStatistic {
 
         match row {
             Some(record) => Ok(record),
-            None => Self::create(conn, date_string),
+            None => Self::create(conn, date_string, clock),
         }
     }
 
-    pub fn load_statistics_history(conn: &Connection, days: i64) -> Result<Vec<TimerStatistic>> {
-        let yesterday = Utc::now().naive_utc() - Duration::days(1);
+    pub fn load_statistics_history(
+        conn: &Connection,
+        today: NaiveDate,
+        days: i64,
+    ) -> Result<Vec<TimerStatistic>> {
+        let yesterday = today - Duration::days(1);
         let x_days_ago = yesterday - Duration::days(days);
 
         // Fetch rows where the date_string is greater than or equal to x_days_ago
@@ -102,8 +354,8 @@ impl TimerStatistic {
     }
 
     // Create a new record in the database with the given date_string
-    pub fn create(conn: &Connection, date_string: &str) -> Result<Self> {
-        let mut new_row = Self::new(date_string.to_string());
+    pub fn create(conn: &Connection, date_string: &str, clock: &dyn Clock) -> Result<Self> {
+        let mut new_row = Self::new(date_string.to_string(), clock);
         conn.execute(
             "INSERT INTO timer_statistics (date_string, timers_started, timers_finished, timers_cancelled, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
             rusqlite::params![new_row.date_string, new_row.timers_started, new_row.timers_finished, new_row.timers_cancelled, new_row.created_at.to_rfc3339(), new_row.updated_at.to_rfc3339()],
@@ -112,27 +364,35 @@ impl TimerStatistic {
         Ok(new_row)
     }
 
-    pub fn increment_timers_started(&mut self, conn: &Connection) -> Result<()> {
+    pub fn increment_timers_started(&mut self, conn: &Connection, clock: &dyn Clock) -> Result<()> {
         self.timers_started += 1;
-        self.save(conn)
+        self.save(conn, clock)
     }
 
-    pub fn increment_timers_finished(&mut self, conn: &Connection) -> Result<()> {
+    pub fn increment_timers_finished(
+        &mut self,
+        conn: &Connection,
+        clock: &dyn Clock,
+    ) -> Result<()> {
         self.timers_finished += 1;
-        self.save(conn)
+        self.save(conn, clock)
     }
 
-    pub fn increment_timers_cancelled(&mut self, conn: &Connection) -> Result<()> {
+    pub fn increment_timers_cancelled(
+        &mut self,
+        conn: &Connection,
+        clock: &dyn Clock,
+    ) -> Result<()> {
         self.timers_cancelled += 1;
-        self.save(conn)
+        self.save(conn, clock)
     }
 
     // Save the current record to the database
-    pub fn save(&self, conn: &Connection) -> Result<()> {
+    pub fn save(&self, conn: &Connection, clock: &dyn Clock) -> Result<()> {
         log::debug!("Saving timer statistic {:?}", self);
         conn.execute(
                 "UPDATE timer_statistics SET timers_started = ?1, timers_finished = ?2, timers_cancelled = ?3, updated_at = ?4 WHERE id = ?5",
-                rusqlite::params![self.timers_started, self.timers_finished, self.timers_cancelled, Utc::now().to_rfc3339(), self.id],
+                rusqlite::params![self.timers_started, self.timers_finished, self.timers_cancelled, clock.now().to_rfc3339(), self.id],
             )?;
         Ok(())
     }
@@ -147,59 +407,82 @@ impl TimerStatistic {
             timers_started: row.get(2)?,
             timers_finished: row.get(3)?,
             timers_cancelled: row.get(4)?,
-            created_at: DateTime::<Utc>::from(
-                DateTime::parse_from_rfc3339(&created_at_column).unwrap(),
-            ),
-            updated_at: DateTime::<Utc>::from(
-                DateTime::parse_from_rfc3339(&updated_at_column).unwrap(),
-            ),
+            created_at: parse_datetime(5, &created_at_column)?,
+            updated_at: parse_datetime(6, &updated_at_column)?,
         })
     }
 }
 
-pub fn load_statistic_for_date(conn: &Connection, date: DateTime<Utc>) -> Result<TimerStatistic> {
-    log::debug!("Loading statistics for {:?}", date);
-
-    let date_string = date.format("%Y-%m-%d").to_string();
+pub fn load_statistic_for_date(
+    conn: &Connection,
+    day: NaiveDate,
+    clock: &dyn Clock,
+) -> Result<TimerStatistic> {
+    log::debug!("Loading statistics for {:?}", day);
 
-    let timer_statistic = TimerStatistic::find_or_create(conn, &date_string)
-        .expect("Could not retrieve daily statistics");
+    let date_string = date_string(day);
 
-    Ok(timer_statistic)
+    TimerStatistic::find_or_create(conn, &date_string, clock)
 }
 
-pub fn mark_timer_started(conn: &Connection, date: DateTime<Utc>) -> Result<()> {
-    log::debug!("Marking timer started at {:?}", date);
-
-    let date_string = date.format("%Y-%m-%d").to_string();
+pub fn mark_timer_started(conn: &Connection, day: NaiveDate, clock: &dyn Clock) -> Result<()> {
+    log::debug!("Marking timer started on {:?}", day);
 
-    let mut timer_statistic = TimerStatistic::find_or_create(conn, &date_string)
-        .expect("Could not retrieve daily statistics");
-    timer_statistic.increment_timers_started(conn);
+    let date_string = date_string(day);
 
-    Ok(())
+    let mut timer_statistic = TimerStatistic::find_or_create(conn, &date_string, clock)?;
+    timer_statistic.increment_timers_started(conn, clock)
 }
 
-pub fn mark_timer_finished(conn: &Connection, date: DateTime<Utc>) -> Result<()> {
-    log::debug!("Marking timer finished at {:?}", date);
+pub fn mark_timer_finished(conn: &Connection, day: NaiveDate, clock: &dyn Clock) -> Result<()> {
+    log::debug!("Marking timer finished on {:?}", day);
 
-    let date_string = date.format("%Y-%m-%d").to_string();
+    let date_string = date_string(day);
 
-    let mut timer_statistic = TimerStatistic::find_or_create(conn, &date_string)
-        .expect("Could not retrieve daily statistics");
-    timer_statistic.increment_timers_finished(conn);
-
-    Ok(())
+    let mut timer_statistic = TimerStatistic::find_or_create(conn, &date_string, clock)?;
+    timer_statistic.increment_timers_finished(conn, clock)
 }
 
-pub fn mark_timer_cancelled(conn: &Connection, date: DateTime<Utc>) -> Result<()> {
-    log::debug!("Marking timer cancelled at {:?}", date);
+pub fn mark_timer_cancelled(conn: &Connection, day: NaiveDate, clock: &dyn Clock) -> Result<()> {
+    log::debug!("Marking timer cancelled on {:?}", day);
+
+    let date_string = date_string(day);
+
+    let mut timer_statistic = TimerStatistic::find_or_create(conn, &date_string, clock)?;
+    timer_statistic.increment_timers_cancelled(conn, clock)
+}
 
-    let date_string = date.format("%Y-%m-%d").to_string();
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Counter {
+    Started,
+    Finished,
+    Cancelled,
+}
 
-    let mut timer_statistic = TimerStatistic::find_or_create(conn, &date_string)
-        .expect("Could not retrieve daily statistics");
-    timer_statistic.increment_timers_cancelled(conn);
+// Moves a day counter by `delta`, for entries edited after they were counted. Counters never
+// drop below zero.
+pub fn adjust_counter(
+    conn: &Connection,
+    day: NaiveDate,
+    counter: Counter,
+    delta: i32,
+    clock: &dyn Clock,
+) -> Result<()> {
+    log::debug!("Adjusting {:?} timers on {:?} by {}", counter, day, delta);
+
+    let date_string = date_string(day);
+
+    let mut timer_statistic = TimerStatistic::find_or_create(conn, &date_string, clock)?;
+    let value = match counter {
+        Counter::Started => &mut timer_statistic.timers_started,
+        Counter::Finished => &mut timer_statistic.timers_finished,
+        Counter::Cancelled => &mut timer_statistic.timers_cancelled,
+    };
+    *value = (*value + delta).max(0);
+    timer_statistic.save(conn, clock)
+}
 
-    Ok(())
+// Statistics are keyed by the day in the calendar of the user, not the UTC date
+fn date_string(day: NaiveDate) -> String {
+    day.format("%Y-%m-%d").to_string()
 }
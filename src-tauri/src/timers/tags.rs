@@ -0,0 +1,104 @@
+use rusqlite::{Connection, Result};
+
+use crate::clock::Clock;
+
+// Separates tag names in the `tags` column read along with an entry. Tags are free text, so
+// a comma could be part of one.
+pub const SEPARATOR: &str = "\u{1f}";
+
+// Trims tags and drops empty ones and repeats. Tags differing only in case are the same tag,
+// the first spelling wins.
+pub fn normalize(tags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+
+    for tag in tags {
+        let tag = tag.trim();
+        if !tag.is_empty() && !normalized.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            normalized.push(tag.to_string());
+        }
+    }
+
+    normalized
+}
+
+// Reads the tags column of an entry row, sorted by name
+pub fn from_column(column: Option<String>) -> Vec<String> {
+    let mut tags: Vec<String> = match column {
+        Some(names) => names.split(SEPARATOR).map(String::from).collect(),
+        None => Vec::new(),
+    };
+    tags.sort_by_key(|t| t.to_lowercase());
+
+    tags
+}
+
+// Replaces the tags of a timer, creating the ones that don't exist yet
+pub fn set_for_timer(
+    conn: &Connection,
+    timer_id: i32,
+    tags: &[String],
+    clock: &dyn Clock,
+) -> Result<()> {
+    conn.execute(
+        "DELETE FROM timer_tags WHERE timer_id = ?1",
+        rusqlite::params![timer_id],
+    )?;
+
+    for name in tags {
+        let tag_id = find_or_create(conn, name, clock)?;
+        conn.execute(
+            "INSERT OR IGNORE INTO timer_tags (timer_id, tag_id) VALUES (?1, ?2)",
+            rusqlite::params![timer_id, tag_id],
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn for_timer(conn: &Connection, timer_id: i32) -> Result<Vec<String>> {
+    let column = conn.query_row(
+        "SELECT group_concat(t.name, char(31)) FROM timer_tags tt JOIN tags t ON t.id = tt.tag_id
+        WHERE tt.timer_id = ?1",
+        rusqlite::params![timer_id],
+        |row| row.get(0),
+    )?;
+
+    Ok(from_column(column))
+}
+
+// Gives a timer every tag of another one it doesn't have yet
+pub fn copy(conn: &Connection, from_timer_id: i32, to_timer_id: i32) -> Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO timer_tags (timer_id, tag_id)
+        SELECT ?2, tag_id FROM timer_tags WHERE timer_id = ?1",
+        rusqlite::params![from_timer_id, to_timer_id],
+    )?;
+    Ok(())
+}
+
+// Every tag ever used, by name
+pub fn list(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT name FROM tags ORDER BY name")?;
+    let rows = stmt.query_map([], |row| row.get(0))?;
+
+    let mut vec: Vec<String> = Vec::new();
+
+    for row in rows {
+        vec.push(row?);
+    }
+
+    Ok(vec)
+}
+
+fn find_or_create(conn: &Connection, name: &str, clock: &dyn Clock) -> Result<i32> {
+    conn.execute(
+        "INSERT OR IGNORE INTO tags (name, created_at) VALUES (?1, ?2)",
+        rusqlite::params![name, clock.now().to_rfc3339()],
+    )?;
+
+    conn.query_row(
+        "SELECT id FROM tags WHERE name = ?1",
+        rusqlite::params![name],
+        |row| row.get(0),
+    )
+}
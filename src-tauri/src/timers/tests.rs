@@ -0,0 +1,2008 @@
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use rusqlite::Connection;
+
+use super::catalogue::CatalogueKind;
+use super::entries::{TimeEntryPatch, TimerEntry, TimerStatus};
+use super::pomodoro::PomodoroPhaseKind;
+use super::projects::Billing;
+use super::service::TimerService;
+use super::statistics::EarningsGrouping;
+use crate::clock::{Calendar, Clock, FakeClock};
+use crate::configuration::{ActiveTimerPolicy, DayGrouping, OverlapPolicy};
+use crate::errors::TimersError;
+use crate::storage::{init_connection, migrations};
+
+fn setup() -> (Connection, FakeClock) {
+    let mut conn = Connection::open_in_memory().unwrap();
+    init_connection(&mut conn).unwrap();
+    migrations::run(&mut conn).unwrap();
+    (conn, FakeClock::new(morning()))
+}
+
+fn morning() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 7, 16, 9, 0, 0).unwrap()
+}
+
+// Makes every write of `operation` on `table` fail, to simulate a crash halfway through a
+// state transition.
+fn fail_on(conn: &Connection, operation: &str, table: &str) {
+    conn.execute_batch(&format!(
+        "CREATE TRIGGER fail_{operation}_{table} BEFORE {operation} ON {table}
+         BEGIN SELECT RAISE(ABORT, 'injected failure'); END;"
+    ))
+    .unwrap();
+}
+
+fn count(conn: &Connection, sql: &str) -> i64 {
+    conn.query_row(sql, [], |row| row.get(0)).unwrap()
+}
+
+#[test]
+fn starts_a_timer_with_activity_and_area() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    let started = service
+        .start(
+            Some(String::from("Coding")),
+            Some(String::from("Work")),
+            Vec::new(),
+        )
+        .unwrap();
+    let active = service.active().unwrap().unwrap();
+
+    assert_eq!(active.id, started.id);
+    assert_eq!(active.activity.as_deref(), Some("Coding"));
+    assert_eq!(active.area.as_deref(), Some("Work"));
+    assert!(active.end_time.is_none());
+    assert!(!active.is_pomodoro);
+}
+
+#[test]
+fn no_active_timer_on_empty_database() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    assert!(service.active().unwrap().is_none());
+    assert!(service.finish().unwrap().is_none());
+    assert!(service.cancel().unwrap().is_none());
+}
+
+#[test]
+fn finishing_a_timer_moves_it_to_history() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    let started = service
+        .start(Some(String::from("Coding")), None, Vec::new())
+        .unwrap();
+    let finished = service.finish().unwrap().unwrap();
+
+    assert_eq!(finished.id, started.id);
+    assert!(finished.end_time.is_some());
+    assert!(service.active().unwrap().is_none());
+
+    let history = service.history().unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].id, started.id);
+
+    let by_date = service.history_by_date().unwrap();
+    assert_eq!(by_date.values().map(|v| v.len()).sum::<usize>(), 1);
+}
+
+#[test]
+fn regular_timers_do_not_touch_pomodoro_statistics() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    service.start(None, None, Vec::new()).unwrap();
+    service.finish().unwrap();
+    let cancelled = service.start(None, None, Vec::new()).unwrap();
+    clock.advance(Duration::minutes(30));
+    service.cancel().unwrap();
+
+    let statistics = service.daily_statistics().unwrap();
+    assert_eq!(statistics.timers_started, 0);
+    assert_eq!(statistics.timers_finished, 0);
+    assert_eq!(statistics.timers_cancelled, 0);
+
+    // Moving one to another day doesn't count it there either
+    service
+        .update_entry(
+            cancelled.id,
+            TimeEntryPatch {
+                start_time: Some(morning() - Duration::days(1)),
+                end_time: Some(morning() - Duration::days(1) + Duration::minutes(30)),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        count(
+            &conn,
+            "SELECT COALESCE(SUM(timers_started + timers_finished + timers_cancelled), 0) FROM timer_statistics"
+        ),
+        0
+    );
+}
+
+#[test]
+fn finishing_a_pomodoro_updates_statistics() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    let started = service
+        .start_pomodoro(Some(String::from("Reading")), Vec::new())
+        .unwrap();
+    assert!(started.is_pomodoro);
+
+    service.finish().unwrap();
+
+    let statistics = service.daily_statistics().unwrap();
+    assert_eq!(statistics.timers_started, 1);
+    assert_eq!(statistics.timers_finished, 1);
+    assert_eq!(statistics.timers_cancelled, 0);
+}
+
+#[test]
+fn cancelling_a_timer_updates_statistics() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    service.start_pomodoro(None, Vec::new()).unwrap();
+    let cancelled = service.cancel().unwrap().unwrap();
+
+    assert!(cancelled.end_time.is_some());
+
+    let statistics = service.daily_statistics().unwrap();
+    assert_eq!(statistics.timers_started, 1);
+    assert_eq!(statistics.timers_finished, 0);
+    assert_eq!(statistics.timers_cancelled, 1);
+}
+
+#[test]
+fn each_command_records_the_timer_status() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    let started = service.start(None, None, Vec::new()).unwrap();
+    assert_eq!(started.status, TimerStatus::Running);
+    assert_eq!(service.pause().unwrap().status, TimerStatus::Paused);
+    assert_eq!(service.resume().unwrap().status, TimerStatus::Running);
+    assert_eq!(
+        service.finish().unwrap().unwrap().status,
+        TimerStatus::Finished
+    );
+
+    clock.advance(Duration::minutes(1));
+    service.start(None, None, Vec::new()).unwrap();
+    assert_eq!(
+        service.cancel().unwrap().unwrap().status,
+        TimerStatus::Cancelled
+    );
+
+    let statuses: Vec<TimerStatus> = service
+        .history()
+        .unwrap()
+        .iter()
+        .map(|entry| entry.status)
+        .collect();
+    assert_eq!(statuses, [TimerStatus::Cancelled, TimerStatus::Finished]);
+}
+
+#[test]
+fn cancelled_entries_can_be_left_out() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    service
+        .start(Some(String::from("Coding")), None, Vec::new())
+        .unwrap();
+    clock.advance(Duration::minutes(10));
+    service.finish().unwrap();
+    service
+        .start(Some(String::from("Coding")), None, Vec::new())
+        .unwrap();
+    clock.advance(Duration::minutes(5));
+    service.cancel().unwrap();
+
+    assert_eq!(service.history().unwrap().len(), 2);
+    assert_eq!(
+        service.activity_statistics().unwrap()[0].total_duration,
+        900
+    );
+
+    let finished_only = TimerService::new(&conn, &clock).with_cancelled(false);
+    assert_eq!(finished_only.history().unwrap().len(), 1);
+    assert_eq!(
+        finished_only
+            .history_by_date()
+            .unwrap()
+            .values()
+            .map(|v| v.len())
+            .sum::<usize>(),
+        1
+    );
+    assert_eq!(
+        finished_only.activity_statistics().unwrap()[0].total_duration,
+        600
+    );
+}
+
+#[test]
+fn discarded_timers_leave_no_trace() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    assert!(service.discard().unwrap().is_none());
+
+    service.start_pomodoro(None, Vec::new()).unwrap();
+    clock.advance(Duration::minutes(2));
+    let discarded = service.discard().unwrap().unwrap();
+
+    assert_eq!(discarded.status, TimerStatus::Discarded);
+    assert!(service.active().unwrap().is_none());
+    assert!(service.pomodoro_phase().unwrap().is_none());
+    assert!(service.history().unwrap().is_empty());
+    assert!(service.activity_statistics().unwrap().is_empty());
+
+    let statistics = service.daily_statistics().unwrap();
+    assert_eq!(statistics.timers_started, 0);
+    assert_eq!(statistics.timers_cancelled, 0);
+
+    // Its time is free to be tracked again
+    let created = service
+        .create_entry(
+            morning(),
+            morning() + Duration::minutes(2),
+            None,
+            None,
+            Vec::new(),
+        )
+        .unwrap();
+    assert_eq!(created.len(), 1);
+}
+
+#[test]
+fn days_follow_the_configured_timezone_and_start_hour() {
+    let (conn, _) = setup();
+    // 01:30 in Madrid on the night clocks go back, before the day starts at 04:00
+    let clock = FakeClock::new(Utc.with_ymd_and_hms(2024, 10, 26, 23, 30, 0).unwrap());
+    let service =
+        TimerService::new(&conn, &clock).with_calendar(Calendar::new(chrono_tz::Europe::Madrid, 4));
+
+    service
+        .start_pomodoro(Some(String::from("Late")), Vec::new())
+        .unwrap();
+    clock.advance(Duration::minutes(20));
+    service.finish().unwrap();
+
+    let statistics = service.daily_statistics().unwrap();
+    assert_eq!(statistics.date_string, "2024-10-26");
+    assert_eq!(statistics.timers_finished, 1);
+    assert_eq!(service.activity_statistics().unwrap().len(), 1);
+
+    // 23:30 local on the 27th is still the 27th, even though the day was 25 hours long
+    clock.advance(Duration::hours(22) + Duration::minutes(40));
+    service
+        .start(Some(String::from("Evening")), None, Vec::new())
+        .unwrap();
+    service.finish().unwrap();
+
+    let statistics = service.daily_statistics().unwrap();
+    assert_eq!(statistics.date_string, "2024-10-27");
+    assert_eq!(statistics.timers_finished, 0);
+
+    let activities = service.activity_statistics().unwrap();
+    assert_eq!(activities.len(), 1);
+    assert_eq!(activities[0].activity.as_deref(), Some("Evening"));
+
+    let history = service.history_by_date().unwrap();
+    let late = NaiveDate::from_ymd_opt(2024, 10, 26).unwrap();
+    let evening = NaiveDate::from_ymd_opt(2024, 10, 27).unwrap();
+    assert_eq!(history[&late][0].activity.as_deref(), Some("Late"));
+    assert_eq!(history[&evening][0].activity.as_deref(), Some("Evening"));
+}
+
+#[test]
+fn entries_remember_the_zone_they_were_started_in() {
+    let (conn, _) = setup();
+    // 00:30 in Madrid, 18:30 the day before in New York
+    let clock = FakeClock::new(Utc.with_ymd_and_hms(2024, 7, 16, 22, 30, 0).unwrap());
+    let madrid =
+        TimerService::new(&conn, &clock).with_calendar(Calendar::new(chrono_tz::Europe::Madrid, 0));
+
+    let started = madrid
+        .start(Some(String::from("Packing")), None, Vec::new())
+        .unwrap();
+    assert_eq!(started.utc_offset, Some(2 * 3600));
+    assert_eq!(started.timezone.as_deref(), Some("Europe/Madrid"));
+    clock.advance(Duration::minutes(20));
+    madrid.finish().unwrap();
+
+    // After the flight
+    let new_york = Calendar::new(chrono_tz::America::New_York, 0);
+    let entry_zone = TimerService::new(&conn, &clock).with_calendar(new_york);
+    let history = entry_zone.history_by_date().unwrap();
+    assert!(history.contains_key(&NaiveDate::from_ymd_opt(2024, 7, 17).unwrap()));
+    assert!(entry_zone.activity_statistics().unwrap().is_empty());
+
+    let current_zone = TimerService::new(&conn, &clock)
+        .with_calendar(new_york.with_grouping(DayGrouping::CurrentZone));
+    let history = current_zone.history_by_date().unwrap();
+    assert!(history.contains_key(&NaiveDate::from_ymd_opt(2024, 7, 16).unwrap()));
+    assert_eq!(current_zone.activity_statistics().unwrap().len(), 1);
+}
+
+fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 7, 16, hour, minute, 0).unwrap()
+}
+
+#[test]
+fn creates_manual_entries_in_the_past() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    let created = service
+        .create_entry(
+            at(7, 0),
+            at(8, 0),
+            Some(String::from("Forgot")),
+            None,
+            Vec::new(),
+        )
+        .unwrap();
+
+    assert_eq!(created.len(), 1);
+    assert!(created[0].is_manual);
+    assert_eq!(created[0].end_time, Some(at(8, 0)));
+    assert_eq!(created[0].duration, 3600);
+    assert_eq!(service.history().unwrap()[0].id, created[0].id);
+}
+
+#[test]
+fn manual_entries_need_a_past_range() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    let backwards = service.create_entry(at(8, 0), at(7, 0), None, None, Vec::new());
+    assert!(matches!(backwards, Err(TimersError::Validation(_))));
+
+    let future = service.create_entry(at(8, 0), at(10, 0), None, None, Vec::new());
+    assert!(matches!(future, Err(TimersError::Validation(_))));
+}
+
+#[test]
+fn overlapping_manual_entries_follow_the_policy() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+    service
+        .create_entry(at(7, 0), at(8, 0), None, None, Vec::new())
+        .unwrap();
+
+    let rejected = service.create_entry(at(7, 30), at(8, 30), None, None, Vec::new());
+    assert!(matches!(rejected, Err(TimersError::Validation(_))));
+
+    let trimmed = TimerService::new(&conn, &clock)
+        .with_overlap_policy(OverlapPolicy::Trim)
+        .create_entry(at(7, 30), at(8, 30), None, None, Vec::new())
+        .unwrap();
+    assert_eq!(trimmed.len(), 1);
+    assert_eq!(trimmed[0].start_time, at(8, 0));
+    assert_eq!(trimmed[0].end_time, Some(at(8, 30)));
+
+    // 06:00 to 08:45 around the two entries leaves two gaps
+    let in_pieces = TimerService::new(&conn, &clock)
+        .with_overlap_policy(OverlapPolicy::Trim)
+        .create_entry(at(6, 0), at(8, 45), None, None, Vec::new());
+    assert!(matches!(in_pieces, Err(TimersError::Validation(_))));
+
+    let split = TimerService::new(&conn, &clock)
+        .with_overlap_policy(OverlapPolicy::Split)
+        .create_entry(at(6, 0), at(8, 45), None, None, Vec::new())
+        .unwrap();
+    let ranges: Vec<_> = split.iter().map(|t| (t.start_time, t.end_time)).collect();
+    assert_eq!(
+        ranges,
+        vec![(at(6, 0), Some(at(7, 0))), (at(8, 30), Some(at(8, 45)))]
+    );
+
+    let covered = TimerService::new(&conn, &clock)
+        .with_overlap_policy(OverlapPolicy::Split)
+        .create_entry(at(7, 0), at(8, 0), None, None, Vec::new());
+    assert!(matches!(covered, Err(TimersError::Validation(_))));
+}
+
+#[test]
+fn manual_entries_stop_where_the_running_timer_started() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock).with_overlap_policy(OverlapPolicy::Trim);
+    service.start(None, None, Vec::new()).unwrap();
+    clock.advance(Duration::minutes(30));
+
+    let trimmed = service
+        .create_entry(at(8, 50), at(9, 10), None, None, Vec::new())
+        .unwrap();
+
+    assert_eq!(trimmed[0].end_time, Some(at(9, 0)));
+    assert!(service.active().unwrap().is_some());
+}
+
+#[test]
+fn edits_every_field_of_an_entry() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+    let created = service
+        .create_entry(
+            at(7, 0),
+            at(8, 0),
+            Some(String::from("Typo")),
+            None,
+            Vec::new(),
+        )
+        .unwrap();
+
+    let updated = service
+        .update_entry(
+            created[0].id,
+            TimeEntryPatch {
+                activity: Some(String::from("Writing")),
+                area: Some(String::from("Work")),
+                tags: Some(vec![String::from("ticket-42")]),
+                start_time: Some(at(7, 30)),
+                end_time: Some(at(8, 15)),
+                is_pomodoro: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(updated.duration, 45 * 60);
+
+    let saved = &service.history().unwrap()[0];
+    assert_eq!(saved.activity.as_deref(), Some("Writing"));
+    assert_eq!(saved.area.as_deref(), Some("Work"));
+    assert_eq!(saved.tags, ["ticket-42"]);
+    assert_eq!(saved.start_time, at(7, 30));
+    assert_eq!(saved.duration, 45 * 60);
+
+    let cleared = service
+        .update_entry(
+            created[0].id,
+            TimeEntryPatch {
+                area: Some(String::new()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(cleared.area, None);
+    assert_eq!(cleared.activity.as_deref(), Some("Writing"));
+}
+
+#[test]
+fn moving_the_start_past_a_pause_stops_counting_it() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+    let started = service.start(None, None, Vec::new()).unwrap();
+    clock.advance(Duration::minutes(10));
+    service.pause().unwrap();
+    clock.advance(Duration::minutes(10));
+    service.resume().unwrap();
+    clock.advance(Duration::minutes(40));
+
+    let moved = TimeEntryPatch {
+        start_time: Some(morning() + Duration::minutes(30)),
+        ..Default::default()
+    };
+    let running = service.update_entry(started.id, moved).unwrap();
+    assert_eq!(running.duration, 30 * 60);
+    assert_eq!(service.active().unwrap().unwrap().duration, 30 * 60);
+
+    let finished = service.finish().unwrap().unwrap();
+    assert_eq!(finished.duration, 30 * 60);
+
+    // Moving it half way into the pause only counts the rest of the pause
+    let into_pause = TimeEntryPatch {
+        start_time: Some(morning() + Duration::minutes(15)),
+        ..Default::default()
+    };
+    let updated = service.update_entry(started.id, into_pause).unwrap();
+    assert_eq!(updated.duration, 40 * 60);
+}
+
+#[test]
+fn edits_keep_entries_valid() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+    let first = service
+        .create_entry(at(6, 0), at(7, 0), None, None, Vec::new())
+        .unwrap();
+    service
+        .create_entry(at(7, 0), at(8, 0), None, None, Vec::new())
+        .unwrap();
+
+    let backwards = service.update_entry(
+        first[0].id,
+        TimeEntryPatch {
+            end_time: Some(at(5, 0)),
+            ..Default::default()
+        },
+    );
+    assert!(matches!(backwards, Err(TimersError::Validation(_))));
+
+    let overlapping = service.update_entry(
+        first[0].id,
+        TimeEntryPatch {
+            end_time: Some(at(7, 30)),
+            ..Default::default()
+        },
+    );
+    assert!(matches!(overlapping, Err(TimersError::Validation(_))));
+
+    let running = service.start(None, None, Vec::new()).unwrap();
+    let ending_running = service.update_entry(
+        running.id,
+        TimeEntryPatch {
+            end_time: Some(at(9, 0)),
+            ..Default::default()
+        },
+    );
+    assert!(matches!(ending_running, Err(TimersError::Validation(_))));
+
+    let missing = service.update_entry(999, TimeEntryPatch::default());
+    assert!(matches!(missing, Err(TimersError::NotFound(_))));
+}
+
+#[test]
+fn moving_a_pomodoro_moves_its_day_counters() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+    let pomodoro = service.start_pomodoro(None, Vec::new()).unwrap();
+    clock.advance(Duration::minutes(25));
+    service.finish().unwrap();
+
+    let yesterday = Duration::days(1);
+    service
+        .update_entry(
+            pomodoro.id,
+            TimeEntryPatch {
+                start_time: Some(at(9, 0) - yesterday),
+                end_time: Some(at(9, 25) - yesterday),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    let counters = |date: &str| {
+        count(
+            &conn,
+            &format!(
+                "SELECT timers_started * 10 + timers_finished FROM timer_statistics WHERE date_string = '{}'",
+                date
+            ),
+        )
+    };
+    assert_eq!(counters("2024-07-16"), 0);
+    assert_eq!(counters("2024-07-15"), 11);
+
+    // No longer a pomodoro, it leaves the counters altogether
+    service
+        .update_entry(
+            pomodoro.id,
+            TimeEntryPatch {
+                is_pomodoro: Some(false),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(counters("2024-07-15"), 0);
+}
+
+#[test]
+fn deleted_entries_move_to_the_trash() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+    let created = service
+        .create_entry(
+            at(7, 0),
+            at(8, 0),
+            Some(String::from("Oops")),
+            None,
+            Vec::new(),
+        )
+        .unwrap();
+    let id = created[0].id;
+
+    let deleted = service.delete_entry(id).unwrap();
+    assert!(deleted.deleted_at.is_some());
+    assert!(service.history().unwrap().is_empty());
+    assert!(service.activity_statistics().unwrap().is_empty());
+    assert_eq!(service.trash().unwrap()[0].id, id);
+
+    let edit = service.update_entry(id, TimeEntryPatch::default());
+    assert!(matches!(edit, Err(TimersError::NotFound(_))));
+
+    // The time is free again while the entry is in the trash
+    service
+        .create_entry(at(7, 0), at(8, 0), None, None, Vec::new())
+        .unwrap();
+}
+
+#[test]
+fn restores_entries_unless_their_time_was_taken() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+    let first = service
+        .create_entry(at(6, 0), at(7, 0), None, None, Vec::new())
+        .unwrap();
+    let second = service
+        .create_entry(at(7, 0), at(8, 0), None, None, Vec::new())
+        .unwrap();
+    service.delete_entry(first[0].id).unwrap();
+    service.delete_entry(second[0].id).unwrap();
+
+    let restored = service.restore_entry(first[0].id).unwrap();
+    assert_eq!(restored.deleted_at, None);
+    assert_eq!(service.history().unwrap().len(), 1);
+
+    service
+        .create_entry(at(7, 30), at(8, 0), None, None, Vec::new())
+        .unwrap();
+    let taken = service.restore_entry(second[0].id);
+    assert!(matches!(taken, Err(TimersError::Validation(_))));
+
+    let not_deleted = service.restore_entry(first[0].id);
+    assert!(matches!(not_deleted, Err(TimersError::NotFound(_))));
+}
+
+#[test]
+fn purging_removes_entries_for_good() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+    let pomodoro = service.start_pomodoro(None, Vec::new()).unwrap();
+    service.pause().unwrap();
+    service.finish().unwrap();
+    let manual = service
+        .create_entry(at(7, 0), at(8, 0), None, None, Vec::new())
+        .unwrap();
+    service.delete_entry(pomodoro.id).unwrap();
+
+    let live = service.purge(Some(manual[0].id));
+    assert!(matches!(live, Err(TimersError::NotFound(_))));
+
+    service.delete_entry(manual[0].id).unwrap();
+    assert_eq!(service.purge(None).unwrap(), 2);
+    assert!(service.trash().unwrap().is_empty());
+    assert_eq!(count(&conn, "SELECT COUNT(*) FROM timers"), 0);
+    assert_eq!(count(&conn, "SELECT COUNT(*) FROM timer_pauses"), 0);
+    assert_eq!(
+        count(
+            &conn,
+            "SELECT COUNT(*) FROM pomodoro_phases WHERE timer_id IS NOT NULL"
+        ),
+        0
+    );
+}
+
+#[test]
+fn deleting_a_timer_removes_its_pauses_and_releases_its_phases() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+    let pomodoro = service
+        .start_pomodoro(None, vec![String::from("deep work")])
+        .unwrap();
+    clock.advance(Duration::minutes(5));
+    service.pause().unwrap();
+    clock.advance(Duration::minutes(5));
+    service.resume().unwrap();
+    service.finish().unwrap();
+
+    conn.execute(
+        "DELETE FROM timers WHERE id = ?1",
+        rusqlite::params![pomodoro.id],
+    )
+    .unwrap();
+
+    assert_eq!(count(&conn, "SELECT COUNT(*) FROM timer_pauses"), 0);
+    assert_eq!(count(&conn, "SELECT COUNT(*) FROM timer_tags"), 0);
+    assert_eq!(count(&conn, "SELECT COUNT(*) FROM tags"), 1);
+    assert_eq!(count(&conn, "SELECT COUNT(*) FROM pomodoro_phases"), 1);
+    assert_eq!(
+        count(
+            &conn,
+            "SELECT COUNT(*) FROM pomodoro_phases WHERE timer_id IS NOT NULL"
+        ),
+        0
+    );
+}
+
+#[test]
+fn running_timers_are_cancelled_rather_than_deleted() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+    let running = service.start(None, None, Vec::new()).unwrap();
+
+    let deleted = service.delete_entry(running.id);
+
+    assert!(matches!(deleted, Err(TimersError::Validation(_))));
+    assert!(service.active().unwrap().is_some());
+}
+
+#[test]
+fn splits_an_entry_in_two() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+    let created = service
+        .create_entry(
+            at(7, 0),
+            at(8, 0),
+            Some(String::from("Coding")),
+            None,
+            Vec::new(),
+        )
+        .unwrap();
+
+    let parts = service
+        .split_entry(created[0].id, at(7, 20), Some(String::from("Email")))
+        .unwrap();
+
+    assert_eq!(parts[0].id, created[0].id);
+    assert_eq!(parts[0].end_time, Some(at(7, 20)));
+    assert_eq!(parts[0].duration, 20 * 60);
+    assert_eq!(parts[1].start_time, at(7, 20));
+    assert_eq!(parts[1].end_time, Some(at(8, 0)));
+    assert_eq!(parts[1].duration, 40 * 60);
+    assert_eq!(parts[1].activity.as_deref(), Some("Email"));
+    assert!(parts[1].is_manual);
+    assert_eq!(service.history().unwrap().len(), 2);
+
+    let outside = service.split_entry(created[0].id, at(7, 30), None);
+    assert!(matches!(outside, Err(TimersError::Validation(_))));
+}
+
+#[test]
+fn splitting_shares_out_the_pauses() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+    let started = service.start(None, None, Vec::new()).unwrap();
+    clock.advance(Duration::minutes(10));
+    service.pause().unwrap();
+    clock.advance(Duration::minutes(10));
+    service.resume().unwrap();
+    clock.advance(Duration::minutes(10));
+    service.finish().unwrap();
+
+    // 09:15 falls inside the pause from 09:10 to 09:20
+    let parts = service.split_entry(started.id, at(9, 15), None).unwrap();
+
+    assert_eq!(parts[0].duration, 10 * 60);
+    assert_eq!(parts[1].duration, 10 * 60);
+}
+
+#[test]
+fn splitting_a_running_timer_keeps_the_second_part_running() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+    let started = service
+        .start(Some(String::from("Coding")), None, Vec::new())
+        .unwrap();
+    clock.advance(Duration::minutes(30));
+
+    let parts = service
+        .split_entry(started.id, at(9, 10), Some(String::from("Review")))
+        .unwrap();
+
+    assert_eq!(parts[0].end_time, Some(at(9, 10)));
+    let active = service.active().unwrap().unwrap();
+    assert_eq!(active.id, parts[1].id);
+    assert_eq!(active.activity.as_deref(), Some("Review"));
+    assert_eq!(active.duration, 20 * 60);
+}
+
+#[test]
+fn merges_entries_that_follow_each_other() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+    let reading = Some(String::from("Reading"));
+    let tracked = service.start(reading.clone(), None, Vec::new()).unwrap();
+    clock.advance(Duration::minutes(30));
+    service.finish().unwrap();
+    clock.advance(Duration::hours(1));
+    let added = service
+        .create_entry(
+            at(9, 31),
+            at(10, 0),
+            reading.clone(),
+            Some(String::from("Home")),
+            Vec::new(),
+        )
+        .unwrap();
+
+    let merged = service
+        .merge_entries(vec![added[0].id, tracked.id])
+        .unwrap();
+
+    assert_eq!(merged.id, tracked.id);
+    assert_eq!(merged.start_time, at(9, 0));
+    assert_eq!(merged.end_time, Some(at(10, 0)));
+    assert_eq!(merged.duration, 59 * 60);
+    assert_eq!(merged.area.as_deref(), Some("Home"));
+    assert_eq!(service.history().unwrap().len(), 1);
+    assert_eq!(service.history().unwrap()[0].duration, 59 * 60);
+
+    // Part of it was added by hand
+    let saved = TimerEntry::find(merged.id, &conn, &clock).unwrap().unwrap();
+    assert!(saved.is_manual);
+}
+
+#[test]
+fn merges_only_adjacent_entries_of_one_activity() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+    let reading = Some(String::from("Reading"));
+    let first = service
+        .create_entry(at(6, 0), at(6, 30), reading.clone(), None, Vec::new())
+        .unwrap();
+    let other = service
+        .create_entry(at(6, 30), at(7, 0), None, None, Vec::new())
+        .unwrap();
+    let last = service
+        .create_entry(at(7, 0), at(7, 30), reading, None, Vec::new())
+        .unwrap();
+
+    let different = service.merge_entries(vec![first[0].id, other[0].id]);
+    assert!(matches!(different, Err(TimersError::Validation(_))));
+
+    let not_adjacent = service.merge_entries(vec![first[0].id, last[0].id]);
+    assert!(matches!(not_adjacent, Err(TimersError::Validation(_))));
+
+    let alone = service.merge_entries(vec![first[0].id, first[0].id]);
+    assert!(matches!(alone, Err(TimersError::Validation(_))));
+}
+
+#[test]
+fn finished_and_cancelled_entries_are_not_merged() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+    let finished = service
+        .create_entry(at(8, 30), at(9, 0), None, None, Vec::new())
+        .unwrap();
+    let cancelled = service.start(None, None, Vec::new()).unwrap();
+    clock.advance(Duration::minutes(30));
+    service.cancel().unwrap();
+
+    let mixed = service.merge_entries(vec![finished[0].id, cancelled.id]);
+    assert!(matches!(mixed, Err(TimersError::Validation(_))));
+    assert_eq!(service.history().unwrap().len(), 2);
+}
+
+#[test]
+fn merging_into_a_running_timer_keeps_it_running() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+    let earlier = service
+        .create_entry(at(8, 0), at(8, 50), None, None, Vec::new())
+        .unwrap();
+    let running = service.start(None, None, Vec::new()).unwrap();
+    clock.advance(Duration::minutes(10));
+
+    let merged = service
+        .merge_entries(vec![earlier[0].id, running.id])
+        .unwrap();
+
+    assert_eq!(merged.end_time, None);
+    let active = service.active().unwrap().unwrap();
+    assert_eq!(active.id, earlier[0].id);
+    assert_eq!(active.duration, 60 * 60);
+}
+
+#[test]
+fn tags_are_cleaned_up_and_shared_between_entries() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    let tags = ["review", " Client A ", "", "Review"].map(String::from);
+    let started = service.start(None, None, tags.to_vec()).unwrap();
+    assert_eq!(started.tags, ["Client A", "review"]);
+
+    clock.advance(Duration::minutes(10));
+    let finished = service.finish().unwrap().unwrap();
+    assert_eq!(finished.tags, ["Client A", "review"]);
+
+    // Spelling differences in case don't make a new tag
+    service
+        .start(None, None, vec![String::from("client a")])
+        .unwrap();
+    assert_eq!(service.tags().unwrap(), ["Client A", "review"]);
+    assert_eq!(service.active().unwrap().unwrap().tags, ["Client A"]);
+}
+
+#[test]
+fn history_and_statistics_filter_and_total_by_tag() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+    let tags = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+    service
+        .create_entry(
+            at(7, 0),
+            at(7, 30),
+            Some(String::from("Coding")),
+            None,
+            tags(&["ticket-1", "client-a"]),
+        )
+        .unwrap();
+    service
+        .create_entry(
+            at(7, 30),
+            at(8, 30),
+            Some(String::from("Coding")),
+            None,
+            tags(&["ticket-2", "client-a"]),
+        )
+        .unwrap();
+    service
+        .create_entry(
+            at(8, 30),
+            at(8, 40),
+            Some(String::from("Email")),
+            None,
+            Vec::new(),
+        )
+        .unwrap();
+
+    let totals: Vec<(String, i32, i32)> = service
+        .tag_statistics()
+        .unwrap()
+        .into_iter()
+        .map(|s| (s.tag, s.total_duration, s.total_timers))
+        .collect();
+    assert_eq!(
+        totals,
+        [
+            (String::from("client-a"), 5400, 2),
+            (String::from("ticket-1"), 1800, 1),
+            (String::from("ticket-2"), 3600, 1),
+        ]
+    );
+
+    let ticket = TimerService::new(&conn, &clock).with_tag(Some(String::from("TICKET-2")));
+    let history = ticket.history().unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].start_time, at(7, 30));
+
+    let activities = ticket.activity_statistics().unwrap();
+    assert_eq!(activities.len(), 1);
+    assert_eq!(activities[0].total_duration, 3600);
+
+    let client = TimerService::new(&conn, &clock).with_tag(Some(String::from("client-a")));
+    assert_eq!(client.history().unwrap().len(), 2);
+    assert_eq!(client.tag_statistics().unwrap().len(), 3);
+}
+
+#[test]
+fn tags_follow_entries_through_split_merge_and_purge() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    let created = service
+        .create_entry(at(7, 0), at(8, 0), None, None, vec![String::from("a")])
+        .unwrap();
+    let parts = service.split_entry(created[0].id, at(7, 30), None).unwrap();
+    assert_eq!(parts[1].tags, ["a"]);
+
+    service
+        .update_entry(
+            parts[1].id,
+            TimeEntryPatch {
+                tags: Some(vec![String::from("b")]),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let merged = service
+        .merge_entries(vec![parts[0].id, parts[1].id])
+        .unwrap();
+    assert_eq!(merged.tags, ["a", "b"]);
+    assert_eq!(count(&conn, "SELECT COUNT(*) FROM timer_tags"), 2);
+
+    service.delete_entry(merged.id).unwrap();
+    service.purge(None).unwrap();
+    assert_eq!(count(&conn, "SELECT COUNT(*) FROM timer_tags"), 0);
+}
+
+#[test]
+fn next_pomodoro_keeps_the_tags() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    service
+        .start_pomodoro(None, vec![String::from("deep-work")])
+        .unwrap();
+    clock.advance(Duration::minutes(25));
+    service.advance_pomodoro().unwrap();
+    clock.advance(Duration::minutes(5));
+
+    let next = service.advance_pomodoro().unwrap().unwrap();
+    assert_eq!(next.timer_entry.unwrap().tags, ["deep-work"]);
+}
+
+#[test]
+fn entries_share_catalogue_activities_and_areas() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    let first = service
+        .create_entry(
+            at(7, 0),
+            at(7, 30),
+            Some(String::from("Review")),
+            Some(String::from("Work")),
+            Vec::new(),
+        )
+        .unwrap();
+    let second = service
+        .create_entry(
+            at(7, 30),
+            at(8, 0),
+            Some(String::from(" review")),
+            None,
+            Vec::new(),
+        )
+        .unwrap();
+
+    assert_eq!(first[0].activity_id, second[0].activity_id);
+    assert_eq!(second[0].activity.as_deref(), Some("Review"));
+
+    let activities = service.activity_statistics().unwrap();
+    assert_eq!(activities.len(), 1);
+    assert_eq!(activities[0].activity_id, first[0].activity_id);
+    assert_eq!(activities[0].total_timers, 2);
+
+    let names: Vec<String> = service
+        .catalogue(CatalogueKind::Area, false)
+        .unwrap()
+        .into_iter()
+        .map(|a| a.name)
+        .collect();
+    assert_eq!(names, ["Work"]);
+}
+
+#[test]
+fn renaming_an_activity_renames_it_everywhere() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    let created = service
+        .create_entry(
+            at(7, 0),
+            at(8, 0),
+            Some(String::from("Reivew")),
+            None,
+            Vec::new(),
+        )
+        .unwrap();
+    service
+        .create_entry(
+            at(8, 0),
+            at(8, 30),
+            Some(String::from("Email")),
+            None,
+            Vec::new(),
+        )
+        .unwrap();
+    let id = created[0].activity_id.unwrap();
+
+    let renamed = service
+        .rename_catalogue_item(CatalogueKind::Activity, id, String::from("Review"))
+        .unwrap();
+    assert_eq!(renamed.name, "Review");
+    assert_eq!(
+        service.history().unwrap()[1].activity.as_deref(),
+        Some("Review")
+    );
+
+    let taken = service.rename_catalogue_item(CatalogueKind::Activity, id, String::from("email"));
+    assert!(matches!(taken, Err(TimersError::Validation(_))));
+    let empty = service.rename_catalogue_item(CatalogueKind::Activity, id, String::from(" "));
+    assert!(matches!(empty, Err(TimersError::Validation(_))));
+    let missing = service.rename_catalogue_item(CatalogueKind::Area, id, String::from("Home"));
+    assert!(matches!(missing, Err(TimersError::NotFound(_))));
+}
+
+#[test]
+fn merging_activities_moves_their_history() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    let typo = service
+        .create_entry(
+            at(7, 0),
+            at(7, 30),
+            Some(String::from("Reivew")),
+            None,
+            Vec::new(),
+        )
+        .unwrap();
+    let right = service
+        .create_entry(
+            at(7, 30),
+            at(8, 30),
+            Some(String::from("Review")),
+            None,
+            Vec::new(),
+        )
+        .unwrap();
+    service.delete_entry(typo[0].id).unwrap();
+
+    let target = service
+        .merge_catalogue_items(
+            CatalogueKind::Activity,
+            typo[0].activity_id.unwrap(),
+            right[0].activity_id.unwrap(),
+        )
+        .unwrap();
+    assert_eq!(target.name, "Review");
+    assert_eq!(
+        service
+            .catalogue(CatalogueKind::Activity, true)
+            .unwrap()
+            .len(),
+        1
+    );
+
+    // Trashed entries follow too, and come back under the merged activity
+    let restored = service.restore_entry(typo[0].id).unwrap();
+    assert_eq!(restored.activity.as_deref(), Some("Review"));
+
+    let activities = service.activity_statistics().unwrap();
+    assert_eq!(activities.len(), 1);
+    assert_eq!(activities[0].total_duration, 90 * 60);
+
+    let itself = service.merge_catalogue_items(CatalogueKind::Activity, target.id, target.id);
+    assert!(matches!(itself, Err(TimersError::Validation(_))));
+}
+
+#[test]
+fn archived_activities_are_hidden_until_used_again() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    let started = service
+        .start(Some(String::from("Old project")), None, Vec::new())
+        .unwrap();
+    service.finish().unwrap();
+    let id = started.activity_id.unwrap();
+
+    let archived = service
+        .set_catalogue_item_archived(CatalogueKind::Activity, id, true)
+        .unwrap();
+    assert!(archived.archived_at.is_some());
+    assert!(service
+        .catalogue(CatalogueKind::Activity, false)
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        service
+            .catalogue(CatalogueKind::Activity, true)
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(
+        service.history().unwrap()[0].activity.as_deref(),
+        Some("Old project")
+    );
+
+    service
+        .start(Some(String::from("Old project")), None, Vec::new())
+        .unwrap();
+    let listed = service.catalogue(CatalogueKind::Activity, false).unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].id, id);
+}
+
+fn billing(billable: Option<bool>, currency: Option<&str>, hourly_rate: Option<i32>) -> Billing {
+    Billing {
+        billable,
+        currency: currency.map(String::from),
+        hourly_rate,
+    }
+}
+
+#[test]
+fn every_entry_resolves_to_a_project() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    let loose = service.start(None, None, Vec::new()).unwrap();
+    assert_eq!(loose.project, "No project");
+
+    let client = service
+        .create_client(String::from("Acme"), Billing::default())
+        .unwrap();
+    let project = service
+        .create_project(Some(client.id), String::from("Website"), Billing::default())
+        .unwrap();
+    let started = service
+        .start(Some(String::from("Design")), None, Vec::new())
+        .unwrap();
+    assert_eq!(started.project_id, loose.project_id);
+
+    service
+        .assign_activity(
+            started.activity_id.unwrap(),
+            Some(project.id),
+            Billing::default(),
+        )
+        .unwrap();
+    let active = service.active().unwrap().unwrap();
+    assert_eq!(active.project_id, project.id);
+    assert_eq!(active.project, "Website");
+
+    // New entries of the activity pick up its project straight away
+    let next = service
+        .start(Some(String::from("design")), None, Vec::new())
+        .unwrap();
+    assert_eq!(next.project_id, project.id);
+}
+
+#[test]
+fn billing_terms_are_inherited_down_the_tree() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    let client = service
+        .create_client(
+            String::from("Acme"),
+            billing(Some(true), Some("eur"), Some(10000)),
+        )
+        .unwrap();
+    assert_eq!(client.billing.currency.as_deref(), Some("EUR"));
+    let standard = service
+        .create_project(Some(client.id), String::from("Support"), Billing::default())
+        .unwrap();
+    let premium = service
+        .create_project(
+            Some(client.id),
+            String::from("Launch"),
+            billing(None, None, Some(12000)),
+        )
+        .unwrap();
+
+    let entry = |start: DateTime<Utc>, minutes: i64, activity: &str| {
+        service
+            .create_entry(
+                start,
+                start + Duration::minutes(minutes),
+                Some(String::from(activity)),
+                None,
+                Vec::new(),
+            )
+            .unwrap()[0]
+            .activity_id
+            .unwrap()
+    };
+    let tickets = entry(at(5, 0), 60, "Tickets");
+    let rollout = entry(at(6, 0), 30, "Rollout");
+    let admin = entry(at(6, 30), 60, "Admin");
+    entry(at(7, 30), 15, "Lunch");
+
+    service
+        .assign_activity(tickets, Some(standard.id), Billing::default())
+        .unwrap();
+    service
+        .assign_activity(rollout, Some(premium.id), Billing::default())
+        .unwrap();
+    service
+        .assign_activity(admin, Some(standard.id), billing(Some(false), None, None))
+        .unwrap();
+
+    let earnings = service
+        .earnings(
+            at(0, 0).date_naive(),
+            at(0, 0).date_naive(),
+            EarningsGrouping::Client,
+        )
+        .unwrap();
+    assert_eq!(earnings.len(), 2);
+
+    // Lunch is on the default project, which has no client and no terms
+    assert_eq!(earnings[0].client_id, None);
+    assert_eq!(earnings[0].total_duration, 15 * 60);
+    assert_eq!(earnings[0].amount, 0);
+
+    let acme = &earnings[1];
+    assert_eq!(acme.client.as_deref(), Some("Acme"));
+    assert_eq!(acme.currency.as_deref(), Some("EUR"));
+    assert_eq!(acme.total_duration, 150 * 60);
+    assert_eq!(acme.billable_duration, 90 * 60);
+    assert_eq!(acme.amount, 10000 + 6000);
+}
+
+#[test]
+fn earnings_add_up_per_day_and_week() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    let project = service
+        .create_project(
+            None,
+            String::from("Consulting"),
+            billing(Some(true), Some("USD"), Some(9000)),
+        )
+        .unwrap();
+
+    // Monday of the week before, Monday and Tuesday of this one
+    let mut activity_id = None;
+    for day in [8, 15, 16] {
+        let start = Utc.with_ymd_and_hms(2024, 7, day, 7, 0, 0).unwrap();
+        let created = service
+            .create_entry(
+                start,
+                start + Duration::minutes(40),
+                Some(String::from("Calls")),
+                None,
+                Vec::new(),
+            )
+            .unwrap();
+        activity_id = created[0].activity_id;
+    }
+    service
+        .assign_activity(activity_id.unwrap(), Some(project.id), Billing::default())
+        .unwrap();
+
+    let first = NaiveDate::from_ymd_opt(2024, 7, 8).unwrap();
+    let last = NaiveDate::from_ymd_opt(2024, 7, 16).unwrap();
+
+    let daily = service
+        .earnings(first, last, EarningsGrouping::Day)
+        .unwrap();
+    let days: Vec<(NaiveDate, i64)> = daily.iter().map(|e| (e.day.unwrap(), e.amount)).collect();
+    assert_eq!(
+        days,
+        [
+            (first, 6000),
+            (NaiveDate::from_ymd_opt(2024, 7, 15).unwrap(), 6000),
+            (last, 6000),
+        ]
+    );
+
+    let weekly = service
+        .earnings(first, last, EarningsGrouping::Week)
+        .unwrap();
+    let weeks: Vec<(NaiveDate, i64)> = weekly.iter().map(|e| (e.day.unwrap(), e.amount)).collect();
+    assert_eq!(
+        weeks,
+        [
+            (first, 6000),
+            (NaiveDate::from_ymd_opt(2024, 7, 15).unwrap(), 12000)
+        ]
+    );
+
+    let backwards = service.earnings(last, first, EarningsGrouping::Day);
+    assert!(matches!(backwards, Err(TimersError::Validation(_))));
+}
+
+#[test]
+fn clients_and_projects_are_validated() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    let client = service
+        .create_client(String::from("Acme"), Billing::default())
+        .unwrap();
+    let duplicate = service.create_client(String::from("acme"), Billing::default());
+    assert!(matches!(duplicate, Err(TimersError::Validation(_))));
+
+    let bad_currency = service.update_client(
+        client.id,
+        String::from("Acme"),
+        billing(None, Some("euro"), None),
+    );
+    assert!(matches!(bad_currency, Err(TimersError::Validation(_))));
+    let negative = service.create_client(String::from("Globex"), billing(None, None, Some(-1)));
+    assert!(matches!(negative, Err(TimersError::Validation(_))));
+
+    service
+        .create_project(Some(client.id), String::from("Website"), Billing::default())
+        .unwrap();
+    let taken =
+        service.create_project(Some(client.id), String::from("Website"), Billing::default());
+    assert!(matches!(taken, Err(TimersError::Validation(_))));
+    // Another client, or none, can use the same name
+    service
+        .create_project(None, String::from("Website"), Billing::default())
+        .unwrap();
+
+    let missing =
+        service.create_project(Some(client.id + 1), String::from("App"), Billing::default());
+    assert!(matches!(missing, Err(TimersError::NotFound(_))));
+
+    let projects = service.projects().unwrap();
+    assert_eq!(projects.len(), 3);
+    assert!(projects[0].is_default);
+}
+
+#[test]
+fn updates_activity_of_an_entry() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    let started = service
+        .start(Some(String::from("Reivew")), None, Vec::new())
+        .unwrap();
+    let updated = service
+        .update_activity(started.id, String::from("Review"))
+        .unwrap();
+
+    assert_eq!(updated.activity.as_deref(), Some("Review"));
+    assert_eq!(
+        service.active().unwrap().unwrap().activity.as_deref(),
+        Some("Review")
+    );
+}
+
+#[test]
+fn updating_a_missing_entry_is_not_found() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    assert!(matches!(
+        service.update_activity(42, String::from("Review")),
+        Err(TimersError::NotFound(_))
+    ));
+}
+
+#[test]
+fn activity_statistics_group_todays_entries() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    for activity in ["Coding", "Coding", "Email"] {
+        service
+            .start(Some(String::from(activity)), None, Vec::new())
+            .unwrap();
+        service.finish().unwrap();
+    }
+
+    let mut statistics = service.activity_statistics().unwrap();
+    statistics.sort_by(|a, b| a.activity.cmp(&b.activity));
+
+    assert_eq!(statistics.len(), 2);
+    assert_eq!(statistics[0].activity.as_deref(), Some("Coding"));
+    assert_eq!(statistics[0].total_timers, 2);
+    assert_eq!(statistics[1].activity.as_deref(), Some("Email"));
+    assert_eq!(statistics[1].total_timers, 1);
+}
+
+#[test]
+fn duration_follows_the_clock() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    let started = service.start(None, None, Vec::new()).unwrap();
+    assert_eq!(started.start_time, morning());
+
+    clock.advance(Duration::minutes(10));
+    assert_eq!(service.active().unwrap().unwrap().duration, 600);
+
+    clock.advance(Duration::minutes(15));
+    let finished = service.finish().unwrap().unwrap();
+
+    assert_eq!(finished.end_time, Some(morning() + Duration::minutes(25)));
+    assert_eq!(finished.duration, 1500);
+    assert_eq!(service.history().unwrap()[0].duration, 1500);
+}
+
+#[test]
+fn statistics_roll_over_to_the_next_day() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    service.start_pomodoro(None, Vec::new()).unwrap();
+    clock.advance(Duration::minutes(25));
+    service.finish().unwrap();
+
+    clock.advance(Duration::days(1));
+    service.start_pomodoro(None, Vec::new()).unwrap();
+    service.cancel().unwrap();
+
+    let today = service.daily_statistics().unwrap();
+    assert_eq!(today.date_string, "2024-07-17");
+    assert_eq!(today.timers_finished, 0);
+    assert_eq!(today.timers_cancelled, 1);
+
+    let history = service.statistics_history(10).unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].date_string, "2024-07-16");
+    assert_eq!(history[0].timers_finished, 1);
+}
+
+#[test]
+fn statistics_history_only_covers_the_requested_window() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    for _ in 0..5 {
+        service.start_pomodoro(None, Vec::new()).unwrap();
+        service.finish().unwrap();
+        clock.advance(Duration::days(1));
+    }
+
+    let dates: Vec<String> = service
+        .statistics_history(2)
+        .unwrap()
+        .into_iter()
+        .map(|s| s.date_string)
+        .collect();
+
+    assert_eq!(dates, vec!["2024-07-20", "2024-07-19", "2024-07-18"]);
+}
+
+#[test]
+fn history_groups_entries_by_day() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    for _ in 0..3 {
+        service.start(None, None, Vec::new()).unwrap();
+        clock.advance(Duration::hours(1));
+        service.finish().unwrap();
+        clock.advance(Duration::hours(12));
+    }
+
+    let by_date = service.history_by_date().unwrap();
+    assert_eq!(by_date.len(), 2);
+    assert_eq!(by_date[&morning().date_naive()].len(), 2);
+}
+
+#[test]
+fn failed_pomodoro_start_does_not_count_as_started() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+    fail_on(&conn, "INSERT", "timers");
+
+    assert!(matches!(
+        service.start_pomodoro(None, Vec::new()),
+        Err(TimersError::Storage(_))
+    ));
+
+    assert_eq!(count(&conn, "SELECT COUNT(*) FROM timer_statistics"), 0);
+    assert_eq!(count(&conn, "SELECT COUNT(*) FROM timers"), 0);
+}
+
+#[test]
+fn failed_finish_leaves_the_timer_running() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    let started = service.start_pomodoro(None, Vec::new()).unwrap();
+    fail_on(&conn, "UPDATE", "timer_statistics");
+
+    clock.advance(Duration::minutes(25));
+    assert!(service.finish().is_err());
+
+    let active = service.active().unwrap().unwrap();
+    assert_eq!(active.id, started.id);
+    assert!(active.end_time.is_none());
+    assert_eq!(
+        count(&conn, "SELECT timers_finished FROM timer_statistics"),
+        0
+    );
+}
+
+#[test]
+fn failed_cancel_leaves_the_timer_running() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    service.start_pomodoro(None, Vec::new()).unwrap();
+    fail_on(&conn, "UPDATE", "timer_statistics");
+
+    assert!(service.cancel().is_err());
+
+    assert!(service.active().unwrap().is_some());
+    assert_eq!(
+        count(&conn, "SELECT timers_cancelled FROM timer_statistics"),
+        0
+    );
+}
+
+#[test]
+fn failed_end_does_not_count_as_cancelled() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    service.start_pomodoro(None, Vec::new()).unwrap();
+    fail_on(&conn, "UPDATE", "timers");
+
+    assert!(service.cancel().is_err());
+
+    assert_eq!(
+        count(&conn, "SELECT timers_cancelled FROM timer_statistics"),
+        0
+    );
+    assert_eq!(
+        count(&conn, "SELECT COUNT(*) FROM timers WHERE end_time IS NULL"),
+        1
+    );
+}
+
+#[test]
+fn starting_a_timer_stops_the_running_one() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    let first = service
+        .start(Some(String::from("Coding")), None, Vec::new())
+        .unwrap();
+    clock.advance(Duration::minutes(30));
+    let second = service
+        .start(Some(String::from("Email")), None, Vec::new())
+        .unwrap();
+
+    assert_eq!(
+        count(&conn, "SELECT COUNT(*) FROM timers WHERE end_time IS NULL"),
+        1
+    );
+    assert_eq!(service.active().unwrap().unwrap().id, second.id);
+
+    let history = service.history().unwrap();
+    assert_eq!(history[0].id, first.id);
+    assert_eq!(history[0].end_time, Some(second.start_time));
+    assert_eq!(history[0].duration, 1800);
+}
+
+#[test]
+fn stopping_a_running_pomodoro_counts_it_as_finished() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    service.start_pomodoro(None, Vec::new()).unwrap();
+    clock.advance(Duration::minutes(5));
+    service.start_pomodoro(None, Vec::new()).unwrap();
+
+    let statistics = service.daily_statistics().unwrap();
+    assert_eq!(statistics.timers_started, 2);
+    assert_eq!(statistics.timers_finished, 1);
+}
+
+#[test]
+fn reject_policy_refuses_to_start_a_second_timer() {
+    let (conn, clock) = setup();
+    let service =
+        TimerService::new(&conn, &clock).with_active_timer_policy(ActiveTimerPolicy::Reject);
+
+    let first = service.start(None, None, Vec::new()).unwrap();
+
+    assert!(matches!(
+        service.start(None, None, Vec::new()),
+        Err(TimersError::Validation(_))
+    ));
+    assert!(matches!(
+        service.start_pomodoro(None, Vec::new()),
+        Err(TimersError::Validation(_))
+    ));
+
+    assert_eq!(service.active().unwrap().unwrap().id, first.id);
+    assert_eq!(count(&conn, "SELECT COUNT(*) FROM timers"), 1);
+    assert_eq!(count(&conn, "SELECT COUNT(*) FROM timer_statistics"), 0);
+}
+
+#[test]
+fn repair_closes_all_but_the_newest_open_entry() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    // Simulate a database written before starting a timer stopped the running one
+    for hour in [9, 10, 12] {
+        conn.execute(
+            "INSERT INTO timers (start_time, is_pomodoro, created_at, updated_at) VALUES (?1, false, ?1, ?1)",
+            rusqlite::params![Utc.with_ymd_and_hms(2024, 7, 16, hour, 0, 0).unwrap().to_rfc3339()],
+        )
+        .unwrap();
+    }
+
+    assert_eq!(service.repair_open_entries().unwrap(), 2);
+    assert_eq!(service.repair_open_entries().unwrap(), 0);
+
+    let active = service.active().unwrap().unwrap();
+    assert_eq!(
+        active.start_time,
+        Utc.with_ymd_and_hms(2024, 7, 16, 12, 0, 0).unwrap()
+    );
+
+    let durations: Vec<i32> = service
+        .history()
+        .unwrap()
+        .iter()
+        .map(|t| t.duration)
+        .collect();
+    assert_eq!(durations, vec![7200, 3600]);
+}
+
+#[test]
+fn repair_counts_closed_pomodoros_as_finished() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    // Both were counted as started when they began
+    conn.execute(
+        "INSERT INTO timer_statistics (date_string, timers_started, timers_finished, timers_cancelled, created_at, updated_at)
+        VALUES ('2024-07-16', 2, 0, 0, ?1, ?1)",
+        rusqlite::params![morning().to_rfc3339()],
+    )
+    .unwrap();
+    for hour in [9, 10] {
+        conn.execute(
+            "INSERT INTO timers (start_time, is_pomodoro, status, created_at, updated_at) VALUES (?1, true, 'running', ?1, ?1)",
+            rusqlite::params![at(hour, 0).to_rfc3339()],
+        )
+        .unwrap();
+    }
+
+    assert_eq!(service.repair_open_entries().unwrap(), 1);
+
+    let statistics = service.daily_statistics().unwrap();
+    assert_eq!(statistics.timers_started, 2);
+    assert_eq!(statistics.timers_finished, 1);
+}
+
+#[test]
+fn paused_time_does_not_count_towards_duration() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    service
+        .start(Some(String::from("Coding")), None, Vec::new())
+        .unwrap();
+    clock.advance(Duration::hours(2));
+
+    let paused = service.pause().unwrap();
+    assert!(paused.paused_at.is_some());
+    assert_eq!(paused.duration, 7200);
+
+    // Lunch break
+    clock.advance(Duration::hours(1));
+    assert_eq!(service.active().unwrap().unwrap().duration, 7200);
+
+    let resumed = service.resume().unwrap();
+    assert!(resumed.paused_at.is_none());
+
+    clock.advance(Duration::hours(1));
+    assert_eq!(service.active().unwrap().unwrap().duration, 10800);
+
+    let finished = service.finish().unwrap().unwrap();
+    assert_eq!(finished.duration, 10800);
+    assert_eq!(service.history().unwrap()[0].duration, 10800);
+}
+
+#[test]
+fn finishing_a_paused_timer_closes_the_pause() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    service.start(None, None, Vec::new()).unwrap();
+    clock.advance(Duration::minutes(20));
+    service.pause().unwrap();
+    clock.advance(Duration::minutes(40));
+
+    let finished = service.finish().unwrap().unwrap();
+
+    assert_eq!(finished.duration, 1200);
+    assert!(finished.paused_at.is_none());
+    assert_eq!(
+        count(
+            &conn,
+            "SELECT COUNT(*) FROM timer_pauses WHERE ended_at IS NULL"
+        ),
+        0
+    );
+}
+
+#[test]
+fn multiple_pauses_add_up() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    service.start(None, None, Vec::new()).unwrap();
+    for _ in 0..3 {
+        clock.advance(Duration::minutes(10));
+        service.pause().unwrap();
+        clock.advance(Duration::minutes(5));
+        service.resume().unwrap();
+    }
+    clock.advance(Duration::minutes(10));
+
+    assert_eq!(service.finish().unwrap().unwrap().duration, 2400);
+}
+
+#[test]
+fn pause_and_resume_validate_the_timer_state() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    assert!(matches!(service.pause(), Err(TimersError::NotFound(_))));
+    assert!(matches!(service.resume(), Err(TimersError::NotFound(_))));
+
+    service.start(None, None, Vec::new()).unwrap();
+    assert!(matches!(service.resume(), Err(TimersError::Validation(_))));
+
+    service.pause().unwrap();
+    assert!(matches!(service.pause(), Err(TimersError::Validation(_))));
+}
+
+#[test]
+fn pomodoro_work_session_ends_on_its_own_and_starts_a_break() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    let started = service
+        .start_pomodoro(Some(String::from("Writing")), Vec::new())
+        .unwrap();
+    let phase = service.pomodoro_phase().unwrap().unwrap();
+    assert_eq!(phase.kind, PomodoroPhaseKind::Work);
+    assert_eq!(phase.timer_id, Some(started.id));
+    assert_eq!(
+        service.pomodoro_deadline().unwrap(),
+        Some(morning() + Duration::minutes(25))
+    );
+
+    clock.advance(Duration::minutes(24));
+    assert!(service.advance_pomodoro().unwrap().is_none());
+
+    clock.advance(Duration::minutes(1));
+    let transition = service.advance_pomodoro().unwrap().unwrap();
+    let finished = transition.timer_entry.unwrap();
+
+    assert_eq!(transition.finished.kind, PomodoroPhaseKind::Work);
+    assert_eq!(finished.end_time, Some(morning() + Duration::minutes(25)));
+    assert_eq!(finished.duration, 1500);
+    assert_eq!(
+        transition.started.unwrap().kind,
+        PomodoroPhaseKind::ShortBreak
+    );
+    assert!(service.active().unwrap().is_none());
+    assert_eq!(service.daily_statistics().unwrap().timers_finished, 1);
+}
+
+#[test]
+fn pomodoro_cycle_ends_with_a_long_break() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    service
+        .start_pomodoro(Some(String::from("Writing")), Vec::new())
+        .unwrap();
+
+    let mut kinds = Vec::new();
+    for _ in 0..9 {
+        let deadline = service.pomodoro_deadline().unwrap().unwrap();
+        clock.advance(deadline - clock.now());
+        let transition = service.advance_pomodoro().unwrap().unwrap();
+        let started = transition.started.unwrap();
+        kinds.push((started.kind, started.cycle));
+    }
+
+    use PomodoroPhaseKind::*;
+    assert_eq!(
+        kinds,
+        vec![
+            (ShortBreak, 1),
+            (Work, 2),
+            (ShortBreak, 2),
+            (Work, 3),
+            (ShortBreak, 3),
+            (Work, 4),
+            (LongBreak, 4),
+            (Work, 1),
+            (ShortBreak, 1),
+        ]
+    );
+
+    // Work sessions started by the engine keep the activity
+    let history = service.history().unwrap();
+    assert!(history
+        .iter()
+        .all(|t| t.is_pomodoro && t.activity.as_deref() == Some("Writing")));
+    assert_eq!(service.daily_statistics().unwrap().timers_started, 5);
+}
+
+#[test]
+fn pomodoro_catches_up_after_a_restart() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    service.start_pomodoro(None, Vec::new()).unwrap();
+    clock.advance(Duration::hours(3));
+
+    let work = service.advance_pomodoro().unwrap().unwrap();
+    assert_eq!(
+        work.timer_entry.unwrap().end_time,
+        Some(morning() + Duration::minutes(25))
+    );
+
+    // The break ran out long ago, so no work session is started in the past
+    let break_ = service.advance_pomodoro().unwrap().unwrap();
+    assert_eq!(break_.finished.kind, PomodoroPhaseKind::ShortBreak);
+    assert!(break_.started.is_none());
+
+    assert!(service.advance_pomodoro().unwrap().is_none());
+    assert!(service.pomodoro_phase().unwrap().is_none());
+    assert!(service.active().unwrap().is_none());
+}
+
+#[test]
+fn pausing_a_pomodoro_moves_its_end() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    service.start_pomodoro(None, Vec::new()).unwrap();
+    clock.advance(Duration::minutes(10));
+    service.pause().unwrap();
+
+    clock.advance(Duration::minutes(30));
+    assert!(service.pomodoro_deadline().unwrap().is_none());
+    assert!(service.advance_pomodoro().unwrap().is_none());
+
+    service.resume().unwrap();
+    assert_eq!(
+        service.pomodoro_deadline().unwrap(),
+        Some(morning() + Duration::minutes(55))
+    );
+
+    clock.advance(Duration::minutes(15));
+    let transition = service.advance_pomodoro().unwrap().unwrap();
+    assert_eq!(transition.timer_entry.unwrap().duration, 1500);
+}
+
+#[test]
+fn moving_the_start_of_a_running_pomodoro_moves_its_end() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    let started = service.start_pomodoro(None, Vec::new()).unwrap();
+    clock.advance(Duration::minutes(10));
+    service
+        .update_entry(
+            started.id,
+            TimeEntryPatch {
+                start_time: Some(morning() - Duration::minutes(5)),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    let phase = service.pomodoro_phase().unwrap().unwrap();
+    assert_eq!(phase.started_at, morning() - Duration::minutes(5));
+    assert_eq!(
+        service.pomodoro_deadline().unwrap(),
+        Some(morning() + Duration::minutes(20))
+    );
+
+    clock.advance(Duration::minutes(10));
+    let transition = service.advance_pomodoro().unwrap().unwrap();
+    assert_eq!(transition.timer_entry.unwrap().duration, 1500);
+}
+
+#[test]
+fn stopping_a_pomodoro_stops_the_cycle() {
+    let (conn, clock) = setup();
+    let service = TimerService::new(&conn, &clock);
+
+    service.start_pomodoro(None, Vec::new()).unwrap();
+    clock.advance(Duration::minutes(25));
+    service.advance_pomodoro().unwrap();
+    assert!(service.pomodoro_phase().unwrap().is_some());
+
+    // Starting work during the break carries on with the set
+    let started = service.start_pomodoro(None, Vec::new()).unwrap();
+    let phase = service.pomodoro_phase().unwrap().unwrap();
+    assert_eq!(phase.timer_id, Some(started.id));
+    assert_eq!(phase.cycle, 2);
+
+    service.finish().unwrap();
+    assert!(service.pomodoro_phase().unwrap().is_none());
+
+    clock.advance(Duration::hours(1));
+    assert!(service.advance_pomodoro().unwrap().is_none());
+}
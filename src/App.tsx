@@ -1,11 +1,24 @@
-import { useState } from "react";
-import { invoke } from "@tauri-apps/api/core";
+import { useEffect, useState } from "react";
 import "./globals.css";
 import TimerTable from "./components/timersTable";
 import { z } from "zod"
 
 import ActiveTimer from "./components/activeTimer";
 import NewTimerForm from "./components/newTimer";
+import {
+  CommandError,
+  finishTimer,
+  getActiveTimer,
+  loadActivityStatisticsForDate,
+  loadConfiguration as loadConfigurationCommand,
+  loadTimerEntriesHistory,
+  onConfigurationChanged,
+  onPomodoroPhaseChanged,
+  startTimer,
+} from "./lib/commands";
+import type { ActivityStatistic } from "./bindings/ActivityStatistic";
+import type { Configuration } from "./bindings/Configuration";
+import type { TimerEntry } from "./bindings/TimerEntry";
 
 
 function formatDuration(duration: number): string {
@@ -21,11 +34,17 @@ function formatDuration(duration: number): string {
 }
 
 function App() {
-  const [timersHistory, setTimersHistory] = useState<any[]>([]);
+  const [timersHistory, setTimersHistory] = useState<TimerEntry[]>([]);
   const [activeTimerChecked, setActiveTimerChecked] = useState<boolean>(false);
-  const [timer, setTimer] = useState<any>(null);
-  const [configuration, setConfiguration] = useState<any>(null);
-  const [dailyStatistics, setDailyStatistics] = useState<any>(null);
+  const [timer, setTimer] = useState<TimerEntry | null>(null);
+  const [configuration, setConfiguration] = useState<Configuration | null>(null);
+  const [dailyStatistics, setDailyStatistics] = useState<ActivityStatistic[] | null>(null);
+  const [error, setError] = useState<CommandError | null>(null);
+
+  const handleError = (e: CommandError) => {
+    console.error(e);
+    setError(e);
+  }
 
   const formSchema = z.object({
     activity: z.string().min(0).max(50),
@@ -46,69 +65,75 @@ function App() {
     } 
 
 
-    invoke("start_timer_command", { activity: values.activity, area: values.area }).then((response) => {
-      setTimer({
-        start_time: new Date().toISOString(),
-        activity: values.activity,
-        area: values.area,
-      })
-      console.debug("Timer started!", response);
+    startTimer(values.activity, values.area).then((timer) => {
+      setTimer(timer);
+      console.debug("Timer started!", timer);
       values.activity = "";
       values.area = "";
-    }).catch((e) => {
-      console.error(e);
-    });
+    }).catch(handleError);
   }
 
   function loadConfiguration() {
-    invoke("load_configuration_command").then((response) => {
-      const config = JSON.parse(response as string);
+    loadConfigurationCommand().then((config) => {
       console.debug("Loaded configuration", config);
       setConfiguration(config);
-    })
+    }).catch(handleError)
   }
 
   const loadActiveTimer = async () => {
-    invoke("get_active_timer_command").then((activeTimer) => {
-      const timer = JSON.parse(activeTimer as string);
+    getActiveTimer().then((timer) => {
       console.debug(timer);
       setTimer(timer);
-    }).catch((e) => {
-      console.error(e);
-    });
+    }).catch(handleError);
   }
 
   const loadActivityStats = () => {
-    invoke("load_activity_statistics_for_date_command").then((response) => {
-      const stats = JSON.parse(response as string);
+    loadActivityStatisticsForDate().then((stats) => {
       console.debug("Loaded daily statistics", stats);
       setDailyStatistics(stats);
-    }).catch((e) => {
-      console.error(e);
-    });
+    }).catch(handleError);
   }
 
   const stopTimer = () => {
     setTimer(null);
-    invoke("finish_timer_command").then(() => {
+    finishTimer().then(() => {
       console.debug("Timer stopped!");
       loadTimersHistory();
       loadActivityStats();
-    }).catch((e) => {
-      console.error(e);
-    });
+    }).catch(handleError);
   }
 
   const loadTimersHistory = () => {
-    invoke("load_timer_entries_history_command").then((timers) => {
-      const timersArray = JSON.parse(timers as string);
-      console.debug("Loaded timers history", timersArray);
-      setTimersHistory(timersArray);
-    }).catch((e) => {
-      console.error(e);
-    });
+    loadTimerEntriesHistory().then((timers) => {
+      console.debug("Loaded timers history", timers);
+      setTimersHistory(timers);
+    }).catch(handleError);
   }
 
+  useEffect(() => {
+    const unlisten = onConfigurationChanged((config) => {
+      console.debug("Configuration changed", config);
+      setConfiguration(config);
+    });
+
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, []);
+
+  useEffect(() => {
+    const unlisten = onPomodoroPhaseChanged((transition) => {
+      console.debug("Pomodoro phase changed", transition);
+      loadActiveTimer();
+      loadTimersHistory();
+      loadActivityStats();
+    });
+
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, []);
+
   if (timersHistory.length === 0) {
     loadTimersHistory();
   }
@@ -144,13 +169,19 @@ function App() {
         }
       </div>
       <div className="p-4 flex flex-col w-full">
+        {error &&
+          <div className="flex justify-between bg-red-100 text-red-800 p-2 mb-2 text-sm">
+            <span>{error.message}</span>
+            <button onClick={() => setError(null)}>Dismiss</button>
+          </div>
+        }
         <div className="flex"> {/* Timer and Daily Statistics*/}
           <div className="w-1/3 px-8"> {/* Daily Statistics*/}
             <p className="text-lg">Daily Activity Statistics</p>
             <div>
-              {dailyStatistics && dailyStatistics.map((stat: any) => {
+              {dailyStatistics && dailyStatistics.map((stat) => {
                 return (
-                  <div key={stat.activity} className="flex w-full justify-between">
+                  <div key={stat.activity_id ?? 'no-activity'} className="flex w-full justify-between">
                     <div className="font-semibold">{stat.activity}</div>
                     <div className="">{formatDuration(stat.total_duration)} ({stat.total_timers})</div>
                   </div>
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Billing } from "./Billing";
+
+export type ActivityBilling = { activity_id: number, activity: string, project_id: number | null, billing: Billing, };
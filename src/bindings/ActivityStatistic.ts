@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ActivityStatistic = { activity_id: number | null, activity: string | null, total_duration: number, total_timers: number, };
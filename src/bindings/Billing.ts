@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Billing = { billable: boolean | null, currency: string | null, hourly_rate: number | null, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type CatalogueItem = { id: number, name: string, archived_at: string | null, created_at: string, updated_at: string, };
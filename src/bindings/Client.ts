@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Billing } from "./Billing";
+
+export type Client = { id: number, name: string, billing: Billing, created_at: string, updated_at: string, };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ConfigurationSources } from "./ConfigurationSources";
+import type { DaySettings } from "./DaySettings";
+import type { NotificationSettings } from "./NotificationSettings";
+import type { PomodoroSettings } from "./PomodoroSettings";
+import type { TimerSettings } from "./TimerSettings";
+
+export type Configuration = { version: string, developmentMode: boolean, configurationPath: string, dbPath: string, logDir: string | null, backupDir: string, exportDir: string, portable: boolean, timers: TimerSettings, pomodoro: PomodoroSettings, notifications: NotificationSettings, day: DaySettings, warnings: Array<string>, sources: ConfigurationSources, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DaySettingsPatch } from "./DaySettingsPatch";
+import type { NotificationSettingsPatch } from "./NotificationSettingsPatch";
+import type { PomodoroSettingsPatch } from "./PomodoroSettingsPatch";
+import type { TimerSettingsPatch } from "./TimerSettingsPatch";
+
+export type ConfigurationPatch = { timers?: TimerSettingsPatch, pomodoro?: PomodoroSettingsPatch, notifications?: NotificationSettingsPatch, day?: DaySettingsPatch, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ValueSource } from "./ValueSource";
+
+export type ConfigurationSources = { developmentMode: ValueSource, configurationPath: ValueSource, dbPath: ValueSource, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DayGrouping } from "./DayGrouping";
+
+export type DaySettings = { start_hour: number, timezone: string | null, group_by: DayGrouping, };
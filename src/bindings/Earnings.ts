@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Earnings = { day: string | null, client_id: number | null, client: string | null, currency: string | null, total_duration: number, billable_duration: number, amount: number, };
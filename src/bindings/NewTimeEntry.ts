@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type NewTimeEntry = { start_time: string, end_time: string, activity: string | null, area: string | null, tags?: Array<string>, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { PomodoroPhaseKind } from "./PomodoroPhaseKind";
+
+export type PomodoroPhase = { id: number, kind: PomodoroPhaseKind, cycle: number, timer_id: number | null, started_at: string, ends_at: string, ended_at: string | null, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type PomodoroSettingsPatch = { work_minutes?: number, short_break_minutes?: number, long_break_minutes?: number, cycles_before_long_break?: number, };
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { PomodoroPhase } from "./PomodoroPhase";
+import type { TimerEntry } from "./TimerEntry";
+
+export type PomodoroTransition = { finished: PomodoroPhase, started: PomodoroPhase | null, timer_entry: TimerEntry | null, };
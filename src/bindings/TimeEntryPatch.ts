@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type TimeEntryPatch = { activity?: string, area?: string, tags?: Array<string>, start_time?: string, end_time?: string, is_pomodoro?: boolean, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TimerStatus } from "./TimerStatus";
+
+export type TimerEntry = { id: number, activity_id: number | null, activity: string | null, area_id: number | null, area: string | null, project_id: number, project: string, tags: Array<string>, start_time: string, end_time: string | null, duration: number, is_pomodoro: boolean, status: TimerStatus, is_manual: boolean, paused_at: string | null, utc_offset: number | null, timezone: string | null, deleted_at: string | null, created_at: string, updated_at: string, };
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ActiveTimerPolicy } from "./ActiveTimerPolicy";
+import type { OverlapPolicy } from "./OverlapPolicy";
+
+export type TimerSettingsPatch = { active_timer_policy?: ActiveTimerPolicy, overlap_policy?: OverlapPolicy, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type TimerStatistic = { id: number, date_string: string, timers_started: number, timers_finished: number, timers_cancelled: number, created_at: string, updated_at: string, };
@@ -1,15 +1,7 @@
 import { ColumnDef } from "@tanstack/react-table"
+import type { TimerEntry } from "@/bindings/TimerEntry"
 
-// This type is used to define the shape of our data.
-// You can use a Zod schema here if you want.
-export type Timer = {
-    id: number
-    activity: string
-    area?: string
-    start_time: string
-    end_time?: string
-    duration: number
-}
+export type Timer = TimerEntry
 
 export const columns: ColumnDef<Timer>[] = [
     {
@@ -0,0 +1,243 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+
+import type { ActivityBilling } from "@/bindings/ActivityBilling";
+import type { ActivityStatistic } from "@/bindings/ActivityStatistic";
+import type { Billing } from "@/bindings/Billing";
+import type { CatalogueItem } from "@/bindings/CatalogueItem";
+import type { CatalogueKind } from "@/bindings/CatalogueKind";
+import type { Client } from "@/bindings/Client";
+import type { Configuration } from "@/bindings/Configuration";
+import type { ConfigurationPatch } from "@/bindings/ConfigurationPatch";
+import type { DayGrouping } from "@/bindings/DayGrouping";
+import type { Earnings } from "@/bindings/Earnings";
+import type { EarningsGrouping } from "@/bindings/EarningsGrouping";
+import type { ErrorPayload } from "@/bindings/ErrorPayload";
+import type { NewTimeEntry } from "@/bindings/NewTimeEntry";
+import type { OverlapPolicy } from "@/bindings/OverlapPolicy";
+import type { PomodoroPhase } from "@/bindings/PomodoroPhase";
+import type { PomodoroTransition } from "@/bindings/PomodoroTransition";
+import type { Project } from "@/bindings/Project";
+import type { TagStatistic } from "@/bindings/TagStatistic";
+import type { TimeEntryPatch } from "@/bindings/TimeEntryPatch";
+import type { TimerEntry } from "@/bindings/TimerEntry";
+import type { TimerStatistic } from "@/bindings/TimerStatistic";
+
+// Typed wrappers around the backend commands. Payload types are generated from the Rust
+// structs by `cargo test` (ts-rs), so any drift between the two sides fails `tsc`.
+
+export type CommandError = ErrorPayload;
+
+// Narrows down history and statistics. Cancelled entries are included unless turned off.
+export type EntryFilter = {
+  includeCancelled?: boolean;
+  tag?: string;
+};
+
+export const loadConfiguration = () =>
+  invoke<Configuration>("load_configuration_command");
+
+export const updateConfiguration = (patch: ConfigurationPatch) =>
+  invoke<Configuration>("update_configuration_command", { patch });
+
+// Emitted by the backend after the settings changed. Resolves to an unlisten function.
+export const onConfigurationChanged = (handler: (configuration: Configuration) => void) =>
+  listen<Configuration>("configuration-changed", (event) => handler(event.payload));
+
+export const startTimer = (
+  activity: string | null,
+  area: string | null,
+  tags: string[] = [],
+) => invoke<TimerEntry>("start_timer_command", { activity, area, tags });
+
+export const startPomodoroTimer = (activity: string | null, tags: string[] = []) =>
+  invoke<TimerEntry>("start_pomodoro_timer_command", { activity, tags });
+
+export const finishTimer = () =>
+  invoke<TimerEntry | null>("finish_timer_command");
+
+export const cancelTimer = () =>
+  invoke<TimerEntry | null>("cancel_timer_command");
+
+// Ends the running timer without keeping it in history or statistics
+export const discardTimer = () =>
+  invoke<TimerEntry | null>("discard_timer_command");
+
+export const pauseTimer = () =>
+  invoke<TimerEntry>("pause_timer_command");
+
+export const resumeTimer = () =>
+  invoke<TimerEntry>("resume_timer_command");
+
+export const getActiveTimer = () =>
+  invoke<TimerEntry | null>("get_active_timer_command");
+
+export const getPomodoroPhase = () =>
+  invoke<PomodoroPhase | null>("get_pomodoro_phase_command");
+
+// Emitted by the backend whenever a pomodoro phase runs out. Resolves to an unlisten function.
+export const onPomodoroPhaseChanged = (handler: (transition: PomodoroTransition) => void) =>
+  listen<PomodoroTransition>("pomodoro-phase-changed", (event) => handler(event.payload));
+
+export const updateTimeEntryActivity = (timeEntryId: number, activity: string) =>
+  invoke<TimerEntry>("update_time_entry_activity_command", { timeEntryId, activity });
+
+export const updateTimeEntry = (timeEntryId: number, patch: TimeEntryPatch) =>
+  invoke<TimerEntry>("update_time_entry_command", { timeEntryId, patch });
+
+// `at` is an RFC 3339 time inside the entry. Returns both parts.
+export const splitTimeEntry = (
+  timeEntryId: number,
+  at: string,
+  newActivity: string | null,
+) =>
+  invoke<TimerEntry[]>("split_time_entry_command", {
+    timeEntryId,
+    at,
+    newActivity,
+  });
+
+export const mergeTimeEntries = (timeEntryIds: number[]) =>
+  invoke<TimerEntry>("merge_time_entries_command", { timeEntryIds });
+
+// Deleted entries go to the trash until they are restored or purged
+export const deleteTimeEntry = (timeEntryId: number) =>
+  invoke<TimerEntry>("delete_time_entry_command", { timeEntryId });
+
+export const restoreTimeEntry = (timeEntryId: number) =>
+  invoke<TimerEntry>("restore_time_entry_command", { timeEntryId });
+
+export const loadTrash = () => invoke<TimerEntry[]>("load_trash_command");
+
+// Without an id the whole trash is emptied
+export const purgeTimeEntries = (timeEntryId?: number) =>
+  invoke<number>("purge_time_entries_command", { timeEntryId });
+
+// `policy` overrides the `timers.overlap_policy` setting
+export const createTimeEntry = (entry: NewTimeEntry, policy?: OverlapPolicy) =>
+  invoke<TimerEntry[]>("create_time_entry_command", { entry, policy });
+
+export const loadTimerEntriesHistory = (filter: EntryFilter = {}) =>
+  invoke<TimerEntry[]>("load_timer_entries_history_command", { ...filter });
+
+// `groupBy` overrides the `day.group_by` setting for this call
+export const loadDailyHistory = (groupBy?: DayGrouping, filter: EntryFilter = {}) =>
+  invoke<Record<string, TimerEntry[]>>("load_daily_history_command", {
+    groupBy,
+    ...filter,
+  });
+
+export const loadDailyStatistics = () =>
+  invoke<TimerStatistic>("load_daily_statistics_command");
+
+export const loadStatisticsHistory = () =>
+  invoke<TimerStatistic[]>("load_statistics_history_command");
+
+export const loadActivityStatisticsForDate = (
+  groupBy?: DayGrouping,
+  filter: EntryFilter = {},
+) =>
+  invoke<ActivityStatistic[]>("load_activity_statistics_for_date_command", {
+    groupBy,
+    ...filter,
+  });
+
+// Time per tag today. Entries with several tags count towards each of them.
+export const loadTagStatisticsForDate = (
+  groupBy?: DayGrouping,
+  filter: EntryFilter = {},
+) =>
+  invoke<TagStatistic[]>("load_tag_statistics_for_date_command", {
+    groupBy,
+    ...filter,
+  });
+
+export const loadTags = () => invoke<string[]>("load_tags_command");
+
+// Activities or areas by name, archived ones only when `includeArchived` is set
+export const loadCatalogue = (kind: CatalogueKind, includeArchived?: boolean) =>
+  invoke<CatalogueItem[]>("load_catalogue_command", { kind, includeArchived });
+
+export const renameCatalogueItem = (kind: CatalogueKind, id: number, name: string) =>
+  invoke<CatalogueItem>("rename_catalogue_item_command", { kind, id, name });
+
+// Files every entry of `sourceId` under `targetId` and removes the source
+export const mergeCatalogueItems = (
+  kind: CatalogueKind,
+  sourceId: number,
+  targetId: number,
+) =>
+  invoke<CatalogueItem>("merge_catalogue_items_command", {
+    kind,
+    sourceId,
+    targetId,
+  });
+
+export const archiveCatalogueItem = (
+  kind: CatalogueKind,
+  id: number,
+  archived: boolean,
+) => invoke<CatalogueItem>("archive_catalogue_item_command", { kind, id, archived });
+
+export const loadClients = () => invoke<Client[]>("load_clients_command");
+
+export const createClient = (name: string, billing: Billing) =>
+  invoke<Client>("create_client_command", { name, billing });
+
+export const updateClient = (clientId: number, name: string, billing: Billing) =>
+  invoke<Client>("update_client_command", { clientId, name, billing });
+
+export const loadProjects = () => invoke<Project[]>("load_projects_command");
+
+export const createProject = (
+  clientId: number | null,
+  name: string,
+  billing: Billing,
+) => invoke<Project>("create_project_command", { clientId, name, billing });
+
+export const updateProject = (
+  projectId: number,
+  clientId: number | null,
+  name: string,
+  billing: Billing,
+) =>
+  invoke<Project>("update_project_command", {
+    projectId,
+    clientId,
+    name,
+    billing,
+  });
+
+export const loadActivityBilling = () =>
+  invoke<ActivityBilling[]>("load_activity_billing_command");
+
+// `projectId` null moves the activity back to the default project
+export const assignActivity = (
+  activityId: number,
+  projectId: number | null,
+  billing: Billing,
+) =>
+  invoke<ActivityBilling>("assign_activity_command", {
+    activityId,
+    projectId,
+    billing,
+  });
+
+// Earnings of the days from `from` to `to` (YYYY-MM-DD), both included. Amounts are in
+// hundredths of their currency.
+export const loadEarnings = (
+  from: string,
+  to: string,
+  grouping: EarningsGrouping,
+  includeCancelled?: boolean,
+) =>
+  invoke<Earnings[]>("load_earnings_command", {
+    from,
+    to,
+    grouping,
+    includeCancelled,
+  });
+
+// Development mode only: shifts the backend clock by `minutes`, 0 resets it.
+export const timeTravel = (minutes: number) =>
+  invoke<string>("time_travel_command", { minutes });